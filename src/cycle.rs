@@ -0,0 +1,117 @@
+//! Finding where a deterministic simulation starts repeating itself.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// The states visited from a start state, `prefix` of them before entering a loop of `period`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cycle<S> {
+    pub prefix: usize,
+    pub period: usize,
+    /// The first states from the start, as many as the detector kept.
+    states: Vec<S>,
+}
+
+impl<S: Clone> Cycle<S> {
+    /// Earliest step index that reaches the same state as `target`.
+    pub fn index(&self, target: usize) -> usize {
+        if target < self.prefix {
+            target
+        } else {
+            self.prefix + (target - self.prefix) % self.period
+        }
+    }
+
+    /// State after `target` steps, simulating from the last kept state if needed.
+    pub fn state_at(&self, target: usize, mut step: impl FnMut(&mut S)) -> S {
+        let index = self.index(target);
+        let kept = index.min(self.states.len() - 1);
+        let mut state = self.states[kept].clone();
+        for _ in kept..index {
+            step(&mut state);
+        }
+        state
+    }
+}
+
+/// Brent's algorithm, only ever holds two states but steps through the loop a few times.
+pub fn brent<S: Clone + PartialEq>(start: S, mut step: impl FnMut(&mut S)) -> Cycle<S> {
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = start.clone();
+    let mut hare = start.clone();
+    step(&mut hare);
+    while tortoise != hare {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+        step(&mut hare);
+        period += 1;
+    }
+
+    // Walk two states `period` apart from the start until they meet at the loop's entry
+    let mut tortoise = start.clone();
+    let mut hare = start.clone();
+    for _ in 0..period {
+        step(&mut hare);
+    }
+    let mut prefix = 0;
+    while tortoise != hare {
+        step(&mut tortoise);
+        step(&mut hare);
+        prefix += 1;
+    }
+    Cycle {
+        prefix,
+        period,
+        states: vec![start],
+    }
+}
+
+/// Remembers every state until one repeats, steps each state only once.
+pub fn hashed<S: Clone + Hash + Eq>(start: S, mut step: impl FnMut(&mut S)) -> Cycle<S> {
+    let mut seen = HashMap::new();
+    let mut states = Vec::new();
+    let mut state = start;
+    loop {
+        if let Some(first) = seen.get(&state) {
+            return Cycle {
+                prefix: *first,
+                period: states.len() - first,
+                states,
+            };
+        }
+        seen.insert(state.clone(), states.len());
+        states.push(state.clone());
+        step(&mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(x: &mut u32) {
+        *x = (*x * *x + 1) % 255;
+    }
+
+    #[test]
+    fn detectors_agree() {
+        for start in 0..255 {
+            let slow = brent(start, step);
+            let fast = hashed(start, step);
+            assert_eq!((slow.prefix, slow.period), (fast.prefix, fast.period));
+            let mut state = start;
+            for target in 0..300 {
+                assert_eq!(state, slow.state_at(target, step));
+                assert_eq!(state, fast.state_at(target, step));
+                step(&mut state);
+            }
+        }
+        let cycle = hashed(3, step);
+        assert_eq!((2, 6), (cycle.prefix, cycle.period));
+        assert_eq!(5, cycle.index(1_000_000_001));
+    }
+}
@@ -1,5 +1,6 @@
 use itertools::Itertools;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
 
 use aoc_downloader::download_day;
 
@@ -14,134 +15,353 @@ fn get_input() -> Vec<String> {
     reader.lines().collect::<Result<_, _>>().unwrap()
 }
 
-type Pos = (usize, usize);
-type Junction = (Pos, Pos);
-type Map = HashMap<Pos, Junction>;
+pub type Pos = (usize, usize);
 
-fn get_north(pos: Pos) -> Option<Pos> {
-    pos.0.checked_sub(1).map(|y| (y, pos.1))
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
 }
 
-fn get_south(pos: Pos, dim: Pos) -> Option<Pos> {
-    if (pos.0 + 1) < dim.0 {
-        Some((pos.0 + 1, pos.1))
-    } else {
-        None
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
+        }
     }
-}
 
-fn get_west(pos: Pos) -> Option<Pos> {
-    pos.1.checked_sub(1).map(|x| (pos.0, x))
+    fn step(self, pos: Pos, dim: Pos) -> Option<Pos> {
+        match self {
+            Direction::North => pos.0.checked_sub(1).map(|y| (y, pos.1)),
+            Direction::East => (pos.1 + 1 < dim.1).then_some((pos.0, pos.1 + 1)),
+            Direction::South => (pos.0 + 1 < dim.0).then_some((pos.0 + 1, pos.1)),
+            Direction::West => pos.1.checked_sub(1).map(|x| (pos.0, x)),
+        }
+    }
 }
 
-fn get_east(pos: Pos, dim: Pos) -> Option<Pos> {
-    if (pos.1 + 1) < dim.1 {
-        Some((pos.0, pos.1 + 1))
-    } else {
-        None
+/// Directions a tile connects to, the start could be any pipe.
+fn openings(tile: char) -> &'static [Direction] {
+    match tile {
+        '|' => &[Direction::North, Direction::South],
+        '-' => &[Direction::East, Direction::West],
+        'L' => &[Direction::North, Direction::East],
+        'J' => &[Direction::North, Direction::West],
+        '7' => &[Direction::South, Direction::West],
+        'F' => &[Direction::East, Direction::South],
+        'S' => &Direction::ALL,
+        '.' => &[],
+        e => panic!("Unknown char encountered: {}", e),
     }
 }
 
-fn get_field(pos: Option<Pos>, values: &[char], input: &[String]) -> Option<Pos> {
-    pos.filter(|&pos| {
-        values
-            .iter()
-            .contains(&input[pos.0].chars().nth(pos.1).unwrap())
-    })
+fn pipe_between(a: Direction, b: Direction) -> char {
+    "|-LJ7F"
+        .chars()
+        .find(|tile| openings(*tile).contains(&a) && openings(*tile).contains(&b))
+        .unwrap()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MazeError {
+    NoStart,
+    NoLoop,
+    AmbiguousLoop(usize),
 }
 
-fn find_start(input: &[String]) -> Option<Pos> {
-    for (y, row) in input.iter().enumerate() {
-        for (x, c) in row.chars().enumerate() {
-            if c == 'S' {
-                return Some((y, x));
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MazeError::NoStart => write!(f, "No start tile"),
+            MazeError::NoLoop => write!(f, "No loop passes through the start"),
+            MazeError::AmbiguousLoop(count) => {
+                write!(f, "{} different loops pass through the start", count)
             }
         }
     }
-    None
-}
-
-fn get_start_pipe(input: &[String], start: Pos, dim: Pos) -> Option<(Pos, Junction)> {
-    let north = get_north(start);
-    let east = get_east(start, dim);
-    let south = get_south(start, dim);
-    let west = get_west(start);
-    let own_north = get_field(north, &['|', '7', 'F'], input);
-    let own_east = get_field(east, &['7', 'J', '-'], input);
-    let own_south = get_field(south, &['|', 'J', 'L'], input);
-    let own_west = get_field(west, &['-', 'L', 'F'], input);
-
-    match (own_north, own_east, own_south, own_west) {
-        (Some(n), None, Some(s), None) => Some((start, (n, s))),
-        (None, Some(e), None, Some(w)) => Some((start, (e, w))),
-        (Some(n), Some(e), None, None) => Some((start, (n, e))),
-        (Some(n), None, None, Some(w)) => Some((start, (n, w))),
-        (None, None, Some(s), Some(w)) => Some((start, (w, s))),
-        (None, Some(e), Some(s), None) => Some((start, (e, s))),
-        e => panic!("Found weird tile: {:?}", e),
-    }
-}
-
-fn parse_input(input: Vec<String>) -> (Pos, Map) {
-    let dimensions = (input.len(), input[0].len());
-    let start = find_start(&input).unwrap();
-    let start_junction = get_start_pipe(&input, start, dimensions);
-    let map = input
-        .iter()
-        .enumerate()
-        .flat_map(|(y, l)| {
-            l.chars().enumerate().filter_map(move |(x, c)| match c {
-                '.' => None,
-                'S' => start_junction,
-                '|' => {
-                    if let (Some(n), Some(s)) = (get_north((y, x)), get_south((y, x), dimensions)) {
-                        Some(((y, x), (n, s)))
-                    } else {
-                        None
-                    }
-                }
-                '-' => {
-                    if let (Some(w), Some(e)) = (get_west((y, x)), get_east((y, x), dimensions)) {
-                        Some(((y, x), (w, e)))
-                    } else {
-                        None
-                    }
-                }
-                'L' => {
-                    if let (Some(n), Some(e)) = (get_north((y, x)), get_east((y, x), dimensions)) {
-                        Some(((y, x), (n, e)))
-                    } else {
-                        None
-                    }
-                }
-                'J' => {
-                    if let (Some(n), Some(w)) = (get_north((y, x)), get_west((y, x))) {
-                        Some(((y, x), (n, w)))
-                    } else {
-                        None
+}
+
+/// Pipes linked to the neighbours that connect back to them.
+pub type Map = HashMap<Pos, Vec<(Direction, Pos)>>;
+
+#[derive(Debug)]
+pub struct PipeMap {
+    pub tiles: Vec<Vec<char>>,
+    pub dimensions: Pos,
+    pub links: Map,
+    pub start: Option<Pos>,
+    /// The pipe hidden under the start tile, once the loop through it is known.
+    pub start_tile: Option<char>,
+    /// The loop through the start, beginning at the start.
+    pub main_loop: Option<Loop>,
+}
+
+impl PipeMap {
+    /// Links up the pipes of any map, whether or not a single loop runs through a start tile.
+    /// An unresolved start connects to every neighbour that connects back to it, so with more
+    /// than two such neighbours no loop is found through it.
+    pub fn scan(input: &[String]) -> Self {
+        let tiles = input
+            .iter()
+            .map(|row| row.chars().collect())
+            .collect::<Vec<Vec<char>>>();
+        let dimensions = (tiles.len(), tiles[0].len());
+        let mut links = Map::new();
+        for (y, row) in tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                for direction in openings(*tile) {
+                    if let Some(next) = direction.step((y, x), dimensions) {
+                        if openings(tiles[next.0][next.1]).contains(&direction.opposite()) {
+                            links.entry((y, x)).or_default().push((*direction, next));
+                        }
                     }
                 }
-                '7' => {
-                    if let (Some(w), Some(s)) = (get_west((y, x)), get_south((y, x), dimensions)) {
-                        Some(((y, x), (w, s)))
-                    } else {
-                        None
+            }
+        }
+        let start = tiles
+            .iter()
+            .enumerate()
+            .find_map(|(y, row)| row.iter().position(|c| *c == 'S').map(|x| (y, x)));
+        PipeMap {
+            tiles,
+            dimensions,
+            links,
+            start,
+            start_tile: None,
+            main_loop: None,
+        }
+    }
+
+    /// Scans the map and resolves the one loop through its start tile.
+    pub fn build(input: &[String]) -> Result<Self, MazeError> {
+        let mut map = PipeMap::scan(input);
+        let start = map.start.ok_or(MazeError::NoStart)?;
+        let links = &map.links;
+        let mut loops = links
+            .get(&start)
+            .into_iter()
+            .flatten()
+            .filter_map(|(direction, next)| {
+                PipeMap::walk_loop(links, start, *next, &mut HashSet::new())
+                    .map(|path| (*direction, path))
+            })
+            .collect::<Vec<_>>();
+        // Every loop is found once in each direction
+        let (first, main_loop) = match loops.len() {
+            0 => return Err(MazeError::NoLoop),
+            2 => loops.remove(0),
+            n => return Err(MazeError::AmbiguousLoop(n / 2)),
+        };
+        let last = links[&start]
+            .iter()
+            .find(|(_, next)| next == main_loop.last().unwrap())
+            .unwrap()
+            .0;
+        map.start_tile = Some(pipe_between(first, last));
+        map.main_loop = Some(Loop { path: main_loop });
+        Ok(map)
+    }
+
+    /// The pipe at `pos`, with the start replaced by the pipe it hides.
+    pub fn tile(&self, pos: Pos) -> char {
+        match self.start_tile {
+            Some(tile) if Some(pos) == self.start => tile,
+            _ => self.tiles[pos.0][pos.1],
+        }
+    }
+
+    /// Tiles enclosed by `path`, found by counting the pipes crossed along each row.
+    pub fn enclosed_by_parity(&self, path: &[Pos]) -> BTreeSet<Pos> {
+        let on_loop = path.iter().collect::<HashSet<_>>();
+        let mut enclosed = BTreeSet::new();
+        for y in 0..self.dimensions.0 {
+            let mut inside = false;
+            for x in 0..self.dimensions.1 {
+                if on_loop.contains(&(y, x)) {
+                    // Only pipes reaching north count, so running along a pipe isn't a crossing
+                    if openings(self.tile((y, x))).contains(&Direction::North) {
+                        inside = !inside;
                     }
+                } else if inside {
+                    enclosed.insert((y, x));
                 }
-                'F' => {
-                    if let (Some(e), Some(s)) =
-                        (get_east((y, x), dimensions), get_south((y, x), dimensions))
-                    {
-                        Some(((y, x), (e, s)))
-                    } else {
-                        None
+            }
+        }
+        enclosed
+    }
+
+    /// Tiles enclosed by `path`, found by flooding the outside on a grid of twice the
+    /// resolution, so the water can squeeze between adjacent pipes.
+    pub fn enclosed_by_flood_fill(&self, path: &[Pos]) -> BTreeSet<Pos> {
+        let dimensions = (2 * self.dimensions.0 + 1, 2 * self.dimensions.1 + 1);
+        let mut blocked = vec![vec![false; dimensions.1]; dimensions.0];
+        for (a, b) in path.iter().circular_tuple_windows() {
+            blocked[2 * a.0 + 1][2 * a.1 + 1] = true;
+            blocked[a.0 + b.0 + 1][a.1 + b.1 + 1] = true;
+        }
+        let mut queue = vec![(0, 0)];
+        blocked[0][0] = true;
+        while let Some(pos) = queue.pop() {
+            for direction in Direction::ALL {
+                if let Some(next) = direction.step(pos, dimensions) {
+                    if !blocked[next.0][next.1] {
+                        blocked[next.0][next.1] = true;
+                        queue.push(next);
                     }
                 }
-                e => panic!("Unknown char encountered: {}", e),
-            })
-        })
-        .collect();
-    (start, map)
+            }
+        }
+        (0..self.dimensions.0)
+            .cartesian_product(0..self.dimensions.1)
+            .filter(|(y, x)| !blocked[2 * y + 1][2 * x + 1])
+            .collect()
+    }
+
+    /// Tiles enclosed by `pipes`, cross-checked between both methods.
+    pub fn enclosed(&self, pipes: &Loop) -> BTreeSet<Pos> {
+        let by_parity = self.enclosed_by_parity(&pipes.path);
+        let by_flood_fill = self.enclosed_by_flood_fill(&pipes.path);
+        assert_eq!(
+            by_parity, by_flood_fill,
+            "Parity scan and flood fill disagree on the enclosed tiles"
+        );
+        by_parity
+    }
+
+    /// Every closed loop of pipes, the main loop first if there is one.
+    pub fn loops(&self) -> Vec<Loop> {
+        let mut loops = self.main_loop.iter().cloned().collect::<Vec<_>>();
+        let mut visited = loops
+            .iter()
+            .flat_map(|main_loop| main_loop.path.iter().copied())
+            .collect::<HashSet<_>>();
+        for pos in self.links.keys().sorted() {
+            if visited.contains(pos) || self.links[pos].len() != 2 {
+                continue;
+            }
+            let next = self.links[pos][0].1;
+            if let Some(path) = PipeMap::walk_loop(&self.links, *pos, next, &mut visited) {
+                loops.push(Loop { path });
+            }
+        }
+        loops
+    }
+
+    /// Pipes that aren't part of any closed loop.
+    pub fn strays(&self) -> Vec<Pos> {
+        let on_loop = self
+            .loops()
+            .into_iter()
+            .flat_map(|found| found.path)
+            .collect::<HashSet<_>>();
+        (0..self.dimensions.0)
+            .cartesian_product(0..self.dimensions.1)
+            .filter(|(y, x)| self.tiles[*y][*x] != '.' && !on_loop.contains(&(*y, *x)))
+            .collect()
+    }
+
+    /// Follows the pipes from `start` through `next` and returns the path if it leads back.
+    /// Every tile walked is added to `visited`, and reaching one that already was gives up:
+    /// a closed loop shares no tiles with anything walked before.
+    fn walk_loop(
+        links: &Map,
+        start: Pos,
+        next: Pos,
+        visited: &mut HashSet<Pos>,
+    ) -> Option<Vec<Pos>> {
+        visited.insert(start);
+        let mut path = vec![start];
+        let mut prev_pos = start;
+        let mut pos = next;
+        while pos != start {
+            if !visited.insert(pos) {
+                return None;
+            }
+            let moves = links.get(&pos).filter(|moves| moves.len() == 2)?;
+            path.push(pos);
+            let forward = if moves[0].1 == prev_pos {
+                moves[1].1
+            } else {
+                moves[0].1
+            };
+            prev_pos = pos;
+            pos = forward;
+        }
+        Some(path)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// A closed loop of pipes, in walking order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Loop {
+    pub path: Vec<Pos>,
+}
+
+impl Loop {
+    pub fn length(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Twice the signed area of the polygon through the tile centres (Shoelace formula).
+    fn signed_area(&self) -> i64 {
+        self.path
+            .iter()
+            .circular_tuple_windows()
+            .map(|(a, b)| determinate(a, b))
+            .sum()
+    }
+
+    /// Number of tiles inside the loop, via Pick's theorem to remove the boundary's tiles.
+    pub fn enclosed_area(&self) -> i64 {
+        self.signed_area().abs() / 2 + 1 - self.length() as i64 / 2
+    }
+
+    /// Orientation as drawn on the map, with rows growing downwards.
+    pub fn orientation(&self) -> Orientation {
+        if self.signed_area() < 0 {
+            Orientation::Clockwise
+        } else {
+            Orientation::CounterClockwise
+        }
+    }
+
+    /// Tile of the loop farthest from `pos` and its distance. From a tile on the loop the
+    /// distance runs along the pipes, from any other tile it is the Manhattan distance.
+    /// Ties go to the tile that comes first in the path.
+    pub fn farthest_from(&self, pos: Pos) -> (Pos, usize) {
+        if let Some(index) = self.path.iter().position(|tile| *tile == pos) {
+            let distance = self.length() / 2;
+            return (self.path[(index + distance) % self.length()], distance);
+        }
+        self.path
+            .iter()
+            .rev()
+            .map(|tile| (*tile, tile.0.abs_diff(pos.0) + tile.1.abs_diff(pos.1)))
+            .max_by_key(|(_, distance)| *distance)
+            .unwrap()
+    }
+}
+
+fn parse_input(input: Vec<String>) -> PipeMap {
+    PipeMap::build(&input).unwrap_or_else(|e| panic!("{}", e))
 }
 
 pub fn run_day() {
@@ -155,23 +375,9 @@ pub fn run_day() {
     );
 }
 
-fn part1(input: &(Pos, Map)) -> usize {
-    let (start, map) = input;
-    let mut pos = map.get(start).unwrap().0;
-    let mut prev_pos = *start;
-    let mut steps = 0;
-    while pos != *start {
-        let moves = map.get(&pos).unwrap();
-        pos = if moves.0 == prev_pos {
-            prev_pos = pos;
-            moves.1
-        } else {
-            prev_pos = pos;
-            moves.0
-        };
-        steps += 1;
-    }
-    steps / 2 + 1
+fn part1(input: &PipeMap) -> usize {
+    let main_loop = input.main_loop.as_ref().unwrap();
+    main_loop.farthest_from(input.start.unwrap()).1
 }
 
 fn determinate(lhs: &Pos, rhs: &Pos) -> i64 {
@@ -179,39 +385,145 @@ fn determinate(lhs: &Pos, rhs: &Pos) -> i64 {
         - TryInto::<i64>::try_into(lhs.1 * rhs.0).unwrap()
 }
 
-fn part2(input: &(Pos, Map)) -> i64 {
-    let (start, map) = input;
-    let mut pos = map.get(start).unwrap().0;
-    let mut prev_pos = *start;
-    let mut boundary = vec![*start, pos];
-    while pos != *start {
-        let moves = map.get(&pos).unwrap();
-        pos = if moves.0 == prev_pos {
-            prev_pos = pos;
-            moves.1
-        } else {
-            prev_pos = pos;
-            moves.0
-        };
-        boundary.push(pos);
-    }
-    // Makes windows easier, as we need start at the end again
-    boundary.push(*start);
-    // Apply Sholace formula, due to start trick we need to add 2 instead of 1 and deduct the
-    // boundaries area
-    boundary
-        .windows(2)
-        .map(|win| determinate(&win[0], &win[1]))
-        .sum::<i64>()
-        .abs()
-        / 2
-        + 2
-        - boundary.len() as i64 / 2
+fn part2(input: &PipeMap) -> i64 {
+    input.main_loop.as_ref().unwrap().enclosed_area()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    #[test]
+    fn part1_sample() {
+        let input = parse_input(sample_lines(
+            r"-L|F7
+7S-7|
+L|7||
+-L-J|
+L|-JF
+",
+        ));
+        assert_eq!(Some('F'), input.start_tile);
+        assert_eq!(4, part1(&input));
+        assert_eq!(1, part2(&input));
+    }
+
+    #[test]
+    fn enclosed_tiles() {
+        let input = parse_input(sample_lines(
+            r"..........
+.S------7.
+.|F----7|.
+.||....||.
+.||....||.
+.|L-7F-J|.
+.|..||..|.
+.L--JL--J.
+..........
+",
+        ));
+        assert_eq!(
+            BTreeSet::from([(6, 2), (6, 3), (6, 6), (6, 7)]),
+            input.enclosed(input.main_loop.as_ref().unwrap())
+        );
+        assert_eq!(4, part2(&input));
+
+        let input = parse_input(sample_lines(
+            r"FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L
+",
+        ));
+        assert_eq!(Some('7'), input.start_tile);
+        assert_eq!(10, input.enclosed(input.main_loop.as_ref().unwrap()).len());
+        assert_eq!(10, part2(&input));
+    }
+
+    #[test]
+    fn several_loops() {
+        let input = parse_input(sample_lines(
+            r"F7.F-7
+LJ.|.|
+.S7L-J
+.LJ...
+",
+        ));
+        let loops = input.loops();
+        assert_eq!(3, loops.len());
+        assert_eq!(vec![(2, 1), (2, 2), (3, 2), (3, 1)], loops[0].path);
+        assert_eq!(vec![(0, 0), (0, 1), (1, 1), (1, 0)], loops[1].path);
+        assert_eq!(
+            vec![4, 4, 8],
+            loops.iter().map(|l| l.length()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![0, 0, 1],
+            loops.iter().map(|l| l.enclosed_area()).collect::<Vec<_>>()
+        );
+        assert_eq!(((2, 5), 4), loops[2].farthest_from((0, 3)));
+        assert_eq!(((2, 5), 7), loops[2].farthest_from((0, 0)));
+        assert_eq!(((0, 3), 2), loops[2].farthest_from((1, 4)));
+        assert_eq!(Orientation::Clockwise, loops[2].orientation());
+        let reversed = Loop {
+            path: loops[2].path.iter().rev().copied().collect(),
+        };
+        assert_eq!(Orientation::CounterClockwise, reversed.orientation());
+
+        // Maps without a single loop through a start can still be analysed
+        let input = PipeMap::scan(&sample_lines("F7F7\nLJ|.\n..L-"));
+        assert_eq!(None, input.start);
+        assert_eq!(1, input.loops().len());
+        assert_eq!(vec![(0, 2), (0, 3), (1, 2), (2, 2), (2, 3)], input.strays());
+        let input = PipeMap::scan(&sample_lines("F7.F7\nLS7LJ\n.LJ.."));
+        assert_eq!(Some((1, 1)), input.start);
+        assert_eq!(None, input.main_loop);
+        let loops = input.loops();
+        assert_eq!(
+            vec![vec![(0, 3), (0, 4), (1, 4), (1, 3)]],
+            loops.iter().map(|l| l.path.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(BTreeSet::new(), input.enclosed(&loops[0]));
+
+        // Dead-end chains are walked once and never reported
+        let input = parse_input(sample_lines("S7.....\nLJ-----\n-------"));
+        assert_eq!(
+            input.main_loop.iter().cloned().collect::<Vec<_>>(),
+            input.loops()
+        );
+    }
+
+    #[test]
+    fn decoy_pipes() {
+        let input = parse_input(sample_lines(
+            r".....
+-S-7.
+.|.|.
+.L-J|
+.....
+",
+        ));
+        assert_eq!(Some('F'), input.start_tile);
+        assert_eq!(8, input.main_loop.as_ref().unwrap().length());
+        assert_eq!(vec![(1, 0), (3, 4)], input.strays());
+        assert_eq!(4, part1(&input));
+        assert_eq!(1, part2(&input));
+        assert_eq!(
+            MazeError::AmbiguousLoop(2),
+            PipeMap::build(&sample_lines("F7.\nLS7\n.LJ")).unwrap_err()
+        );
+        assert_eq!(
+            MazeError::NoLoop,
+            PipeMap::build(&sample_lines("-S-")).unwrap_err()
+        );
+    }
 
     #[test]
     fn part1_output() {
@@ -1,4 +1,5 @@
-use itertools::Itertools;
+use num::BigUint;
+use std::iter::zip;
 
 use aoc_downloader::download_day;
 
@@ -13,8 +14,8 @@ fn get_input() -> Vec<String> {
     reader.lines().collect::<Result<_, _>>().unwrap()
 }
 
-type Input = (Vec<Pos>, XGaps, YGaps);
-type Pos = (usize, usize);
+pub type Input = (Vec<Pos>, YGaps, XGaps);
+pub type Pos = (usize, usize);
 type XGaps = Vec<usize>;
 type YGaps = Vec<usize>;
 
@@ -28,7 +29,7 @@ where
         .collect()
 }
 
-fn parse_input(input: Vec<String>) -> Input {
+pub fn parse_input(input: Vec<String>) -> Input {
     let positions = input
         .iter()
         .enumerate()
@@ -79,43 +80,230 @@ pub fn run_day() {
     );
 }
 
+/// How many rows and columns every empty row and column grows into.
+#[derive(Clone, Copy, Debug)]
+pub struct Expansion {
+    pub rows: usize,
+    pub columns: usize,
+}
+
+impl Expansion {
+    pub fn uniform(factor: usize) -> Self {
+        Expansion {
+            rows: factor,
+            columns: factor,
+        }
+    }
+}
+
 fn manhatten_distance(pos_a: &Pos, pos_b: &Pos) -> usize {
     (pos_a.0 as isize - pos_b.0 as isize).unsigned_abs()
         + (pos_a.1 as isize - pos_b.1 as isize).unsigned_abs()
 }
 
-fn expand(input: &Input, expansion_rate: usize) -> Vec<Pos> {
+pub fn expand(input: &Input, expansion: &Expansion) -> Vec<Pos> {
     let (positions, ygaps, xgaps) = input;
     positions
         .iter()
         .map(|(y, x)| {
+            // Replace every empty line before the galaxy by `factor` lines, none for a factor of 0
+            let ygaps = ygaps.partition_point(|ygap| ygap < y);
+            let xgaps = xgaps.partition_point(|xgap| xgap < x);
             (
-                y + ygaps.iter().filter(|ygap| y > ygap).count() * expansion_rate,
-                x + xgaps.iter().filter(|xgap| x > xgap).count() * expansion_rate,
+                y - ygaps + ygaps * expansion.rows,
+                x - xgaps + xgaps * expansion.columns,
             )
         })
         .collect::<Vec<Pos>>()
 }
 
-fn part1(input: &Input) -> usize {
-    expand(input, 1)
+/// Sum of the distances between all pairs of `coordinates` on a line.
+fn axis_distance_sum(mut coordinates: Vec<usize>) -> usize {
+    coordinates.sort_unstable();
+    // Every coordinate is the larger one in as many pairs as there are coordinates before it
+    coordinates
         .iter()
-        .combinations(2)
-        .map(|vec| manhatten_distance(vec[0], vec[1]))
-        .sum()
+        .enumerate()
+        .fold((0, 0), |(sum, prefix), (i, coordinate)| {
+            (sum + i * coordinate - prefix, prefix + coordinate)
+        })
+        .0
 }
 
-fn part2(input: &Input) -> usize {
-    expand(input, 999_999)
+/// Sum of the distances between all pairs of galaxies.
+pub fn distance_sum(input: &Input, expansion: &Expansion) -> usize {
+    let (ys, xs) = expand(input, expansion).into_iter().unzip();
+    axis_distance_sum(ys) + axis_distance_sum(xs)
+}
+
+/// The distance sum as a linear function of the expansion factors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DistanceFormula {
+    /// Sum with every empty row and column removed.
+    pub constant: usize,
+    /// Growth of the sum per unit of the row factor.
+    pub rows: usize,
+    /// Growth of the sum per unit of the column factor.
+    pub columns: usize,
+}
+
+impl DistanceFormula {
+    pub fn new(input: &Input) -> Self {
+        let (positions, ygaps, xgaps) = input;
+        let gaps = positions
+            .iter()
+            .map(|(y, x)| {
+                (
+                    ygaps.partition_point(|ygap| ygap < y),
+                    xgaps.partition_point(|xgap| xgap < x),
+                )
+            })
+            .collect::<Vec<Pos>>();
+        // Collapsing the gaps keeps the order of the galaxies on both axes, so the distances
+        // of the collapsed positions and of the gap counts add up independently
+        let (ys, xs) = zip(positions, &gaps)
+            .map(|(pos, gap)| (pos.0 - gap.0, pos.1 - gap.1))
+            .unzip();
+        let (ygap_counts, xgap_counts) = gaps.into_iter().unzip();
+        DistanceFormula {
+            constant: axis_distance_sum(ys) + axis_distance_sum(xs),
+            rows: axis_distance_sum(ygap_counts),
+            columns: axis_distance_sum(xgap_counts),
+        }
+    }
+
+    /// `(a, b)` such that the sum for a uniform factor `k` is `a + b * k`.
+    pub fn coefficients(&self) -> (usize, usize) {
+        (self.constant, self.rows + self.columns)
+    }
+
+    pub fn eval(&self, rows: &BigUint, columns: &BigUint) -> BigUint {
+        BigUint::from(self.constant) + rows * self.rows + columns * self.columns
+    }
+
+    pub fn eval_uniform(&self, factor: &BigUint) -> BigUint {
+        self.eval(factor, factor)
+    }
+}
+
+pub fn distance_matrix(input: &Input, expansion: &Expansion) -> Vec<Vec<usize>> {
+    let positions = expand(input, expansion);
+    positions
         .iter()
-        .combinations(2)
-        .map(|vec| manhatten_distance(vec[0], vec[1]))
-        .sum()
+        .map(|a| positions.iter().map(|b| manhatten_distance(a, b)).collect())
+        .collect()
+}
+
+/// Index of and distance to another galaxy.
+type Neighbour = Option<(usize, usize)>;
+
+fn closest_by(matrix: &[Vec<usize>], pick: fn(Vec<(usize, usize)>) -> Neighbour) -> Vec<Neighbour> {
+    matrix
+        .iter()
+        .enumerate()
+        .map(|(galaxy, distances)| {
+            pick(
+                distances
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .filter(|(other, _)| *other != galaxy)
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+/// Index of and distance to the nearest other galaxy, for every galaxy.
+pub fn nearest(matrix: &[Vec<usize>]) -> Vec<Neighbour> {
+    closest_by(matrix, |distances| {
+        distances.into_iter().min_by_key(|(_, distance)| *distance)
+    })
+}
+
+/// Index of and distance to the farthest other galaxy, for every galaxy, ties go to the lower index.
+pub fn farthest(matrix: &[Vec<usize>]) -> Vec<Neighbour> {
+    closest_by(matrix, |distances| {
+        distances
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, distance)| *distance)
+    })
+}
+
+fn part1(input: &Input) -> usize {
+    let (a, b) = DistanceFormula::new(input).coefficients();
+    a + b * 2
+}
+
+fn part2(input: &Input) -> usize {
+    let (a, b) = DistanceFormula::new(input).coefficients();
+    a + b * 1_000_000
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+    use itertools::Itertools;
+
+    const SAMPLE: &str = r"...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....
+";
+
+    #[test]
+    fn part1_sample() {
+        let input = parse_input(sample_lines(SAMPLE));
+        assert_eq!(374, part1(&input));
+        assert_eq!(1030, distance_sum(&input, &Expansion::uniform(10)));
+        assert_eq!(8410, distance_sum(&input, &Expansion::uniform(100)));
+    }
+
+    #[test]
+    fn distance_formula() {
+        let input = parse_input(sample_lines(SAMPLE));
+        let formula = DistanceFormula::new(&input);
+        assert_eq!((210, 82), formula.coefficients());
+        assert_eq!(210, distance_sum(&input, &Expansion::uniform(0)));
+        for (rows, columns) in [(1, 1), (2, 5), (10, 10), (1_000_000, 3)] {
+            assert_eq!(
+                BigUint::from(distance_sum(&input, &Expansion { rows, columns })),
+                formula.eval(&BigUint::from(rows), &BigUint::from(columns))
+            );
+        }
+        let huge = BigUint::from(10_u32).pow(30);
+        assert_eq!(
+            BigUint::from(210_u32) + &huge * 82_u32,
+            formula.eval_uniform(&huge)
+        );
+    }
+
+    #[test]
+    fn distance_queries() {
+        let input = parse_input(sample_lines(SAMPLE));
+        let expansion = Expansion {
+            rows: 3,
+            columns: 7,
+        };
+        let matrix = distance_matrix(&input, &expansion);
+        let pairwise = (0..matrix.len())
+            .tuple_combinations()
+            .map(|(a, b)| matrix[a][b])
+            .sum::<usize>();
+        assert_eq!(pairwise, distance_sum(&input, &expansion));
+        let matrix = distance_matrix(&input, &Expansion::uniform(2));
+        assert_eq!(9, matrix[4][8]);
+        assert_eq!(Some((6, 5)), nearest(&matrix)[8]);
+        assert_eq!(Some((5, 15)), farthest(&matrix)[0]);
+    }
 
     #[test]
     fn part1_output() {
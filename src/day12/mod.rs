@@ -1,5 +1,6 @@
 use aoc_downloader::download_day;
-use cached::proc_macro::cached;
+
+pub mod nonogram;
 
 const DAY: u32 = 12;
 
@@ -12,9 +13,9 @@ fn get_input() -> Vec<String> {
     reader.lines().collect::<Result<_, _>>().unwrap()
 }
 
-type Input = Vec<(String, Vec<usize>)>;
+pub type Input = Vec<(String, Vec<usize>)>;
 
-fn parse_input(input: Vec<String>) -> Input {
+pub fn parse_input(input: Vec<String>) -> Input {
     input
         .iter()
         .map(|line| {
@@ -42,55 +43,243 @@ pub fn run_day() {
     );
 }
 
-#[cached]
-fn arrangement(map: Vec<u8>, groups: Vec<usize>) -> usize {
-    if map.is_empty() {
-        return if groups.is_empty() { 1 } else { 0 };
+/// Length of the stretch without operational springs starting at each cell.
+fn runs(cells: &[u8]) -> Vec<usize> {
+    let mut runs = vec![0; cells.len() + 1];
+    for i in (0..cells.len()).rev() {
+        runs[i] = if cells[i] == b'.' { 0 } else { runs[i + 1] + 1 };
     }
-    match map[0] {
-        b'.' => return arrangement(map[1..].to_owned(), groups),
-        b'?' => {
-            return arrangement([&[b'.'], &map[1..]].concat(), groups.to_owned())
-                + arrangement([&[b'#'], &map[1..]].concat(), groups.to_owned())
+    runs
+}
+
+/// Ways to place `groups[j..]` into `cells[i..]`, at index `i * (groups.len() + 1) + j`.
+fn suffix_counts(cells: &[u8], groups: &[usize]) -> Vec<u128> {
+    let width = groups.len() + 1;
+    let runs = runs(cells);
+    let mut ways = vec![0; (cells.len() + 1) * width];
+    ways[cells.len() * width + groups.len()] = 1;
+    for i in (0..cells.len()).rev() {
+        if !matches!(cells[i], b'.' | b'#' | b'?') {
+            panic!("Unexpected character: {}", cells[i]);
         }
-        b'#' => {
-            if groups.is_empty()
-                || map.len() < groups[0]
-                || map[..groups[0]].iter().any(|c| *c == b'.')
-            {
-                return 0;
+        for j in (0..width).rev() {
+            let mut count = 0;
+            if cells[i] != b'#' {
+                count += ways[(i + 1) * width + j];
             }
-            if groups.len() > 1 {
-                if map.len() < (groups[0] + 1) || map[groups[0]] == b'#' {
-                    return 0;
+            if let Some(&group) = groups.get(j) {
+                let end = i + group;
+                if runs[i] >= group && cells.get(end) != Some(&b'#') {
+                    count += ways[(end + 1).min(cells.len()) * width + j + 1];
                 }
-                return arrangement(map[(groups[0] + 1)..].to_owned(), groups[1..].to_owned());
-            } else {
-                return arrangement(map[(groups[0])..].to_owned(), groups[1..].to_owned());
             }
+            ways[i * width + j] = count;
         }
-        e => panic!("Unexpected character: {}", e),
     }
+    ways
+}
+
+pub fn arrangements(cells: &[u8], groups: &[usize]) -> u128 {
+    suffix_counts(cells, groups)[0]
+}
+
+/// Arrangements of a row copied `factor` times, joined by unknown springs.
+pub fn unfolded_arrangements(cells: &[u8], groups: &[usize], factor: usize) -> u128 {
+    let cells = vec![cells; factor].join(&b'?');
+    arrangements(&cells, &groups.repeat(factor))
 }
 
-fn part1(input: &Input) -> usize {
+/// A row of springs prepared for questions about its concrete arrangements.
+pub struct Row<'a> {
+    cells: &'a [u8],
+    groups: &'a [usize],
+    runs: Vec<usize>,
+    ways: Vec<u128>,
+}
+
+impl<'a> Row<'a> {
+    pub fn new(cells: &'a [u8], groups: &'a [usize]) -> Self {
+        Row {
+            cells,
+            groups,
+            runs: runs(cells),
+            ways: suffix_counts(cells, groups),
+        }
+    }
+
+    fn ways(&self, i: usize, j: usize) -> u128 {
+        self.ways[i * (self.groups.len() + 1) + j]
+    }
+
+    /// Whether group `j` can start at cell `i` with operational springs (or the edge) around it.
+    fn fits(&self, i: usize, j: usize) -> bool {
+        let end = i + self.groups[j];
+        self.runs[i] >= self.groups[j]
+            && self.cells.get(end) != Some(&b'#')
+            && (i == 0 || self.cells[i - 1] != b'#')
+    }
+
+    pub fn count(&self) -> u128 {
+        self.ways(0, 0)
+    }
+
+    /// The arrangement at `index`, in the order where operational springs come first.
+    pub fn nth(&self, mut index: u128) -> Option<Vec<u8>> {
+        if index >= self.count() {
+            return None;
+        }
+        let mut arrangement = Vec::with_capacity(self.cells.len());
+        let (mut i, mut j) = (0, 0);
+        while i < self.cells.len() {
+            if self.cells[i] != b'#' {
+                let operational = self.ways(i + 1, j);
+                if index < operational {
+                    arrangement.push(b'.');
+                    i += 1;
+                    continue;
+                }
+                index -= operational;
+            }
+            let end = i + self.groups[j];
+            arrangement.extend(std::iter::repeat_n(b'#', self.groups[j]));
+            if end < self.cells.len() {
+                arrangement.push(b'.');
+            }
+            i = (end + 1).min(self.cells.len());
+            j += 1;
+        }
+        Some(arrangement)
+    }
+
+    /// Lazily walks through every arrangement.
+    pub fn iter(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        (0..self.count()).map_while(|index| self.nth(index))
+    }
+
+    /// A uniformly drawn arrangement, `random(n)` has to return a uniform number below `n`.
+    pub fn random(&self, random: impl FnOnce(u128) -> u128) -> Option<Vec<u8>> {
+        if self.count() == 0 {
+            return None;
+        }
+        self.nth(random(self.count()))
+    }
+
+    /// Number of arrangements in which each spring is damaged.
+    pub fn damage_counts(&self) -> Vec<u128> {
+        let cells = self.cells.iter().rev().copied().collect::<Vec<u8>>();
+        let groups = self.groups.iter().rev().copied().collect::<Vec<usize>>();
+        // Ways to place `groups[..j]` into `cells[..i]`, taken from the reversed row
+        let prefixes = suffix_counts(&cells, &groups);
+        let prefix = |i: usize, j: usize| {
+            prefixes[(self.cells.len() - i) * (self.groups.len() + 1) + self.groups.len() - j]
+        };
+        let mut changes = vec![0_i128; self.cells.len() + 1];
+        for i in 0..self.cells.len() {
+            for (j, group) in self.groups.iter().enumerate() {
+                if !self.fits(i, j) {
+                    continue;
+                }
+                let before = if i == 0 {
+                    u128::from(j == 0)
+                } else {
+                    prefix(i - 1, j)
+                };
+                let after = self.ways((i + group + 1).min(self.cells.len()), j + 1);
+                let count = (before * after) as i128;
+                changes[i] += count;
+                changes[i + group] -= count;
+            }
+        }
+        changes
+            .iter()
+            .take(self.cells.len())
+            .scan(0, |damaged, change| {
+                *damaged += change;
+                Some(*damaged as u128)
+            })
+            .collect()
+    }
+
+    /// Share of all arrangements in which each spring is damaged.
+    pub fn damage_probabilities(&self) -> Option<Vec<f64>> {
+        let total = self.count();
+        if total == 0 {
+            return None;
+        }
+        Some(
+            self.damage_counts()
+                .iter()
+                .map(|damaged| *damaged as f64 / total as f64)
+                .collect(),
+        )
+    }
+}
+
+fn part1(input: &Input) -> u128 {
     input
         .iter()
-        .map(|(map, groups)| arrangement(map.as_bytes().to_owned(), groups.to_vec()))
+        .map(|(map, groups)| arrangements(map.as_bytes(), groups))
         .sum()
 }
 
-fn part2(input: &Input) -> usize {
+fn part2(input: &Input) -> u128 {
     input
         .iter()
-        .map(|(map, groups)| (vec![map.clone(); 5].join("?"), groups.repeat(5)))
-        .map(|(map, groups)| arrangement(map.as_bytes().to_owned(), groups.to_vec()))
+        .map(|(map, groups)| unfolded_arrangements(map.as_bytes(), groups, 5))
         .sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    const SAMPLE: &str = r"???.### 1,1,3
+.??..??...?##. 1,1,3
+?#?#?#?#?#?#?#? 1,3,1,6
+????.#...#... 4,1,1
+????.######..#####. 1,6,5
+?###???????? 3,2,1
+";
+
+    #[test]
+    fn part1_sample() {
+        let input = parse_input(sample_lines(SAMPLE));
+        assert_eq!(21, part1(&input));
+        assert_eq!(525152, part2(&input));
+    }
+
+    #[test]
+    fn concrete_arrangements() {
+        let row = Row::new(b"?###????????", &[3, 2, 1]);
+        let all = row.iter().collect::<Vec<_>>();
+        assert_eq!(10, all.len());
+        assert_eq!(b".###....##.#".to_vec(), all[0]);
+        assert_eq!(b".###.##.#...".to_vec(), all[9]);
+        assert!(all.windows(2).all(|pair| pair[0] != pair[1]));
+        assert_eq!(None, row.nth(10));
+        assert_eq!(Some(all[3].clone()), row.random(|n| n / 3));
+
+        let probabilities = row.damage_probabilities().unwrap();
+        for (i, probability) in probabilities.iter().enumerate() {
+            let damaged = all.iter().filter(|a| a[i] == b'#').count();
+            assert!((probability - damaged as f64 / 10.).abs() < 1e-9);
+        }
+        assert_eq!(None, Row::new(b"#.#", &[3]).damage_probabilities());
+    }
+
+    #[test]
+    fn unfold_factors() {
+        assert_eq!(1, unfolded_arrangements(b"???.###", &[1, 1, 3], 10));
+        assert_eq!(
+            506250,
+            unfolded_arrangements(b"?###????????", &[3, 2, 1], 5)
+        );
+        assert_eq!(0, arrangements(b"#.#", &[3]));
+        assert_eq!(1, arrangements(b"", &[]));
+        // 30 single springs in 89 unknowns: choose their positions among 60 slots
+        assert_eq!(118264581564861424, unfolded_arrangements(b"??", &[1], 30));
+    }
 
     #[test]
     fn part1_output() {
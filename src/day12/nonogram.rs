@@ -0,0 +1,146 @@
+use super::Row;
+
+/// A picross puzzle given by the damaged groups of every row and column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Nonogram {
+    pub rows: Vec<Vec<usize>>,
+    pub columns: Vec<Vec<usize>>,
+}
+
+pub type Grid = Vec<Vec<u8>>;
+
+impl Nonogram {
+    /// Reads the row clues, a blank line and the column clues, one comma separated clue per
+    /// line. A line reading `0` stands for an empty line of the puzzle.
+    pub fn parse(input: &[String]) -> Self {
+        let mut sections = input.split(|line| line.is_empty()).map(|section| {
+            section
+                .iter()
+                .map(|line| {
+                    line.split(',')
+                        .map(|n| n.trim().parse().unwrap())
+                        .filter(|n| *n != 0)
+                        .collect()
+                })
+                .collect()
+        });
+        Nonogram {
+            rows: sections.next().unwrap(),
+            columns: sections.next().unwrap(),
+        }
+    }
+
+    pub fn from_file(path: &str) -> Self {
+        use std::io::BufRead;
+
+        let file = std::fs::File::open(path).unwrap();
+        let reader = std::io::BufReader::new(&file);
+        Nonogram::parse(&reader.lines().collect::<Result<Vec<_>, _>>().unwrap())
+    }
+
+    /// Fills in every cell that has the same state in all arrangements of its line. Returns
+    /// `None` if a line can't be arranged at all.
+    fn line_solve(&self, mut grid: Grid) -> Option<Grid> {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (y, clue) in self.rows.iter().enumerate() {
+                changed |= solve_line(&mut grid[y], clue)?;
+            }
+            for (x, clue) in self.columns.iter().enumerate() {
+                let mut column = grid.iter().map(|row| row[x]).collect::<Vec<u8>>();
+                if solve_line(&mut column, clue)? {
+                    changed = true;
+                    grid.iter_mut()
+                        .zip(column)
+                        .for_each(|(row, cell)| row[x] = cell);
+                }
+            }
+        }
+        Some(grid)
+    }
+
+    fn backtrack(&self, grid: Grid) -> Option<Grid> {
+        let grid = self.line_solve(grid)?;
+        let Some((y, x)) = grid
+            .iter()
+            .enumerate()
+            .find_map(|(y, row)| row.iter().position(|c| *c == b'?').map(|x| (y, x)))
+        else {
+            return Some(grid);
+        };
+        [b'#', b'.'].into_iter().find_map(|guess| {
+            let mut guessed = grid.clone();
+            guessed[y][x] = guess;
+            self.backtrack(guessed)
+        })
+    }
+
+    pub fn solve(&self) -> Option<Grid> {
+        self.backtrack(vec![vec![b'?'; self.columns.len()]; self.rows.len()])
+    }
+}
+
+/// Settles the cells of `line` that agree in all arrangements, `None` if there are none.
+fn solve_line(line: &mut [u8], clue: &[usize]) -> Option<bool> {
+    let row = Row::new(line, clue);
+    let total = row.count();
+    if total == 0 {
+        return None;
+    }
+    let damage = row.damage_counts();
+    let mut changed = false;
+    for (cell, damaged) in line.iter_mut().zip(damage) {
+        let settled = match damaged {
+            0 => b'.',
+            d if d == total => b'#',
+            _ => continue,
+        };
+        changed |= *cell != settled;
+        *cell = settled;
+    }
+    Some(changed)
+}
+
+pub fn render(grid: &Grid) -> String {
+    grid.iter()
+        .map(|row| String::from_utf8_lossy(row))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::sample_lines;
+
+    #[test]
+    fn solve_heart() {
+        let puzzle = Nonogram::parse(&sample_lines(
+            r"1,1
+5
+5
+3
+1
+
+2
+4
+4
+4
+2",
+        ));
+        assert_eq!(vec![1, 1], puzzle.rows[0]);
+        assert_eq!(
+            ".#.#.\n#####\n#####\n.###.\n..#..",
+            render(&puzzle.solve().unwrap())
+        );
+    }
+
+    #[test]
+    fn backtracking() {
+        let ambiguous = Nonogram::parse(&sample_lines("1\n1\n\n1\n1"));
+        assert_eq!("#.\n.#", render(&ambiguous.solve().unwrap()));
+        let impossible = Nonogram::parse(&sample_lines("2\n0\n\n1\n0"));
+        assert_eq!(None, impossible.solve());
+    }
+}
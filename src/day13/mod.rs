@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::iter::zip;
 
 use aoc_downloader::download_day;
@@ -51,88 +52,345 @@ where
         .collect()
 }
 
-fn line_to_number(line: &[char]) -> usize {
-    usize::from_str_radix(
-        &line
-            .iter()
-            .map(|c| if *c == '.' { '0' } else { '1' })
-            .collect::<String>(),
-        2,
-    )
-    .unwrap()
+/// Orientation of a mirror line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    /// Between two rows.
+    Horizontal,
+    /// Between two columns.
+    Vertical,
 }
 
-fn find_symmetry(grid: &[Vec<char>]) -> Option<usize> {
-    let encoded = grid
-        .iter()
-        .map(|line| line_to_number(line))
-        .collect::<Vec<usize>>();
-
-    for i in 1..encoded.len() {
-        let (left, right) = encoded.split_at(i);
-        let left = left.iter().rev().copied().collect::<Vec<usize>>();
+/// A mirror line with the mirrored cell pairs that disagree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reflection {
+    pub axis: Axis,
+    /// Rows or columns before the line.
+    pub line: usize,
+    pub mismatches: usize,
+    /// (row, column) of both cells of every disagreeing pair.
+    pub smudges: Vec<[(usize, usize); 2]>,
+}
 
-        let size = left.len().min(right.len());
-        if left[..size] == right[..size] {
-            return Some(i);
+impl Reflection {
+    pub fn summary(&self) -> usize {
+        match self.axis {
+            Axis::Horizontal => self.line * 100,
+            Axis::Vertical => self.line,
         }
     }
-    None
 }
 
-fn get_symmetry_line(grid: &Vec<Vec<char>>) -> usize {
-    if let Some(normal) = find_symmetry(grid) {
-        return normal * 100;
-    }
-    if let Some(transposed) = find_symmetry(&transpose(grid.to_vec())) {
-        return transposed;
-    }
-    panic!("No symmetry found");
+type Smudges = Vec<[(usize, usize); 2]>;
+
+/// Mirror lines between rows with at most `tolerance` disagreeing cell pairs.
+fn row_reflections(grid: &[Vec<char>], tolerance: usize) -> Vec<(usize, Smudges)> {
+    (1..grid.len())
+        .filter_map(|line| {
+            let mut smudges = Vec::new();
+            for (above, below) in zip((0..line).rev(), line..grid.len()) {
+                for (column, (a, b)) in zip(&grid[above], &grid[below]).enumerate() {
+                    if a != b {
+                        if smudges.len() == tolerance {
+                            return None;
+                        }
+                        smudges.push([(above, column), (below, column)]);
+                    }
+                }
+            }
+            Some((line, smudges))
+        })
+        .collect()
 }
 
-fn part1(input: &Input) -> usize {
-    input.iter().map(get_symmetry_line).sum()
+/// Every mirror line of `grid` with at most `tolerance` smudges, horizontal lines first.
+pub fn reflections(grid: &[Vec<char>], tolerance: usize) -> Vec<Reflection> {
+    let horizontal = row_reflections(grid, tolerance)
+        .into_iter()
+        .map(|(line, smudges)| (Axis::Horizontal, line, smudges));
+    let vertical = row_reflections(&transpose(grid.to_vec()), tolerance)
+        .into_iter()
+        .map(|(line, smudges)| {
+            let smudges = smudges
+                .into_iter()
+                .map(|pair| pair.map(|(row, column)| (column, row)))
+                .collect();
+            (Axis::Vertical, line, smudges)
+        });
+    horizontal
+        .chain(vertical)
+        .map(|(axis, line, smudges)| Reflection {
+            axis,
+            line,
+            mismatches: smudges.len(),
+            smudges,
+        })
+        .collect()
 }
 
-fn get_smudge(grid: &Vec<Vec<char>>) -> Option<usize> {
-    let encoded = grid
-        .iter()
-        .map(|line| line_to_number(line))
-        .collect::<Vec<usize>>();
-
-    for i in 1..encoded.len() {
-        let (left, right) = encoded.split_at(i);
-
-        let difference = zip(left.iter().rev(), right)
-            .filter(|(l, r)| l != r)
-            .map(|(l, r)| l ^ r)
-            .collect::<Vec<usize>>();
-        // As there is only one defect in the grid there must a) be only on difference
-        // and b) that difference must be a power of two (one bit)
-        if difference.len() == 1 && (difference[0] & (difference[0] - 1)) == 0 {
-            return Some(i);
+/// A way of mapping a pattern onto itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symmetry {
+    Reflection(Axis, usize),
+    /// Mirrored along the top-left to bottom-right diagonal, square patterns only.
+    Diagonal,
+    /// Mirrored along the top-right to bottom-left diagonal, square patterns only.
+    AntiDiagonal,
+    /// Unchanged by a 180° turn.
+    HalfTurn,
+    /// Unchanged by a 90° turn, square patterns only.
+    QuarterTurn,
+    /// Every row repeats the given number of rows further down.
+    RowPeriod(usize),
+    /// Every column repeats the given number of columns further right.
+    ColumnPeriod(usize),
+}
+
+/// A symmetry that holds once `mismatches` cells are fixed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymmetryMatch {
+    pub symmetry: Symmetry,
+    pub mismatches: usize,
+    /// (row, column) of the cells of every orbit that disagrees.
+    pub smudges: Vec<Vec<(usize, usize)>>,
+}
+
+impl Symmetry {
+    /// Where the symmetry moves the cell at `(row, column)`, if it stays inside the pattern.
+    fn image(
+        &self,
+        (height, width): (usize, usize),
+        (row, column): (usize, usize),
+    ) -> Option<(usize, usize)> {
+        match *self {
+            Symmetry::Reflection(Axis::Horizontal, line) => {
+                Some(((2 * line).checked_sub(row + 1)?, column)).filter(|(row, _)| *row < height)
+            }
+            Symmetry::Reflection(Axis::Vertical, line) => {
+                Some((row, (2 * line).checked_sub(column + 1)?))
+                    .filter(|(_, column)| *column < width)
+            }
+            Symmetry::Diagonal => Some((column, row)),
+            Symmetry::AntiDiagonal => Some((width - 1 - column, height - 1 - row)),
+            Symmetry::HalfTurn => Some((height - 1 - row, width - 1 - column)),
+            Symmetry::QuarterTurn => Some((column, width - 1 - row)),
+            Symmetry::RowPeriod(period) => {
+                Some((row + period, column)).filter(|(row, _)| *row < height)
+            }
+            Symmetry::ColumnPeriod(period) => {
+                Some((row, column + period)).filter(|(_, column)| *column < width)
+            }
         }
     }
-    None
-}
 
-fn get_fixed_symmetry_line(grid: &Vec<Vec<char>>) -> usize {
-    if let Some(normal) = get_smudge(grid) {
-        return normal * 100;
+    /// Cells that the symmetry forces to be equal, `None` if it doesn't apply to the shape.
+    fn orbits(&self, dimensions: (usize, usize)) -> Option<Vec<Vec<(usize, usize)>>> {
+        let (height, width) = dimensions;
+        let square_only = matches!(
+            self,
+            Symmetry::Diagonal | Symmetry::AntiDiagonal | Symmetry::QuarterTurn
+        );
+        if square_only && height != width {
+            return None;
+        }
+        let mut seen = vec![vec![false; width]; height];
+        let mut orbits = Vec::new();
+        // Periods move cells forward only, so visiting in reading order starts each chain at its head
+        for start in (0..height).flat_map(|row| (0..width).map(move |column| (row, column))) {
+            let mut orbit = Vec::new();
+            let mut cell = Some(start);
+            while let Some((row, column)) = cell.filter(|(row, column)| !seen[*row][*column]) {
+                seen[row][column] = true;
+                orbit.push((row, column));
+                cell = self.image(dimensions, (row, column));
+            }
+            if orbit.len() > 1 {
+                orbits.push(orbit);
+            }
+        }
+        Some(orbits)
     }
-    if let Some(transposed) = get_smudge(&transpose(grid.to_vec())) {
-        return transposed;
+
+    /// Checks the symmetry against `grid`, fixing each orbit to its most common cell.
+    pub fn check(&self, grid: &[Vec<char>], tolerance: usize) -> Option<SymmetryMatch> {
+        let mut mismatches = 0;
+        let mut smudges = Vec::new();
+        for orbit in self.orbits((grid.len(), grid[0].len()))? {
+            let mut counts = HashMap::new();
+            for (row, column) in &orbit {
+                *counts.entry(grid[*row][*column]).or_insert(0) += 1;
+            }
+            let fixes = orbit.len() - counts.values().max().unwrap();
+            if fixes > 0 {
+                mismatches += fixes;
+                if mismatches > tolerance {
+                    return None;
+                }
+                smudges.push(orbit);
+            }
+        }
+        Some(SymmetryMatch {
+            symmetry: *self,
+            mismatches,
+            smudges,
+        })
     }
-    panic!("No symmetry found");
+}
+
+/// Every symmetry of `grid` with at most `tolerance` smudges, mirror lines first.
+pub fn symmetries(grid: &[Vec<char>], tolerance: usize) -> Vec<SymmetryMatch> {
+    let (height, width) = (grid.len(), grid[0].len());
+    let mirrors = reflections(grid, tolerance)
+        .into_iter()
+        .map(|reflection| SymmetryMatch {
+            symmetry: Symmetry::Reflection(reflection.axis, reflection.line),
+            mismatches: reflection.mismatches,
+            smudges: reflection
+                .smudges
+                .iter()
+                .map(|pair| pair.to_vec())
+                .collect(),
+        });
+    let others = [
+        Symmetry::Diagonal,
+        Symmetry::AntiDiagonal,
+        Symmetry::HalfTurn,
+        Symmetry::QuarterTurn,
+    ]
+    .into_iter()
+    .chain((1..height).map(Symmetry::RowPeriod))
+    .chain((1..width).map(Symmetry::ColumnPeriod))
+    .filter_map(|symmetry| symmetry.check(grid, tolerance));
+    mirrors.chain(others).collect()
+}
+
+/// Summary of the one mirror line that needs exactly `smudges` cells fixed.
+fn get_symmetry_line(grid: &[Vec<char>], smudges: usize) -> usize {
+    reflections(grid, smudges)
+        .iter()
+        .find(|reflection| reflection.mismatches == smudges)
+        .expect("No symmetry found")
+        .summary()
+}
+
+fn part1(input: &Input) -> usize {
+    input.iter().map(|grid| get_symmetry_line(grid, 0)).sum()
 }
 
 fn part2(input: &Input) -> usize {
-    input.iter().map(get_fixed_symmetry_line).sum()
+    input.iter().map(|grid| get_symmetry_line(grid, 1)).sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    const SAMPLE: &str = r"#.##..##.
+..#.##.#.
+##......#
+##......#
+..#.##.#.
+..##..##.
+#.#.##.#.
+
+#...##..#
+#....#..#
+..##..###
+#####.##.
+#####.##.
+..##..###
+#....#..#";
+
+    #[test]
+    fn part1_sample() {
+        let input = parse_input(sample_lines(SAMPLE));
+        assert_eq!(405, part1(&input));
+        assert_eq!(400, part2(&input));
+    }
+
+    #[test]
+    fn smudge_tolerance() {
+        let input = parse_input(sample_lines(SAMPLE));
+        assert_eq!(
+            vec![Reflection {
+                axis: Axis::Vertical,
+                line: 5,
+                mismatches: 0,
+                smudges: vec![],
+            }],
+            reflections(&input[0], 0)
+        );
+        assert_eq!(
+            Reflection {
+                axis: Axis::Horizontal,
+                line: 3,
+                mismatches: 1,
+                smudges: vec![[(0, 0), (5, 0)]],
+            },
+            reflections(&input[0], 1)[0]
+        );
+        let two = reflections(&input[1], 2);
+        assert!(two.iter().all(|reflection| reflection.mismatches <= 2));
+        assert!(two.contains(&Reflection {
+            axis: Axis::Horizontal,
+            line: 4,
+            mismatches: 0,
+            smudges: vec![],
+        }));
+    }
+
+    #[test]
+    fn other_symmetries() {
+        let grid = ["#..", ".#.", "..#"].map(|line| line.chars().collect::<Vec<char>>());
+        let exact = symmetries(&grid, 0)
+            .into_iter()
+            .map(|found| found.symmetry)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                Symmetry::Diagonal,
+                Symmetry::AntiDiagonal,
+                Symmetry::HalfTurn
+            ],
+            exact
+        );
+        assert_eq!(
+            Some(SymmetryMatch {
+                symmetry: Symmetry::QuarterTurn,
+                mismatches: 2,
+                smudges: vec![vec![(0, 0), (0, 2), (2, 2), (2, 0)]],
+            }),
+            Symmetry::QuarterTurn.check(&grid, 2)
+        );
+        assert_eq!(None, Symmetry::QuarterTurn.check(&grid, 1));
+
+        let tiles = ["#.#.#.", ".#.###", "#.#.#."].map(|line| line.chars().collect::<Vec<char>>());
+        assert_eq!(None, Symmetry::Diagonal.check(&tiles, 10));
+        assert_eq!(None, Symmetry::ColumnPeriod(2).check(&tiles, 0));
+        assert_eq!(
+            Some(SymmetryMatch {
+                symmetry: Symmetry::ColumnPeriod(2),
+                mismatches: 1,
+                smudges: vec![vec![(1, 0), (1, 2), (1, 4)]],
+            }),
+            Symmetry::ColumnPeriod(2).check(&tiles, 1)
+        );
+        assert_eq!(
+            Some(0),
+            Symmetry::RowPeriod(2)
+                .check(&tiles, 0)
+                .map(|found| found.mismatches)
+        );
+        // Mirror lines agree with the dedicated finder
+        let input = parse_input(sample_lines(SAMPLE));
+        assert_eq!(
+            Some(reflections(&input[0], 1)[0].smudges[0].to_vec()),
+            Symmetry::Reflection(Axis::Horizontal, 3)
+                .check(&input[0], 1)
+                .map(|found| found.smudges[0].clone())
+        );
+    }
 
     #[test]
     fn part1_output() {
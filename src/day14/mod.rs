@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::fmt;
+
+use crate::cycle;
 
 use aoc_downloader::download_day;
 
@@ -13,98 +15,228 @@ fn get_input() -> Vec<String> {
     reader.lines().collect::<Result<_, _>>().unwrap()
 }
 
-type Input = Vec<Vec<char>>;
-
-fn parse_input(input: Vec<String>) -> Input {
-    input.iter().map(|row| row.chars().collect()).collect()
+/// Direction the dish is tilted in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tilt {
+    North,
+    West,
+    South,
+    East,
 }
 
-pub fn run_day() {
-    let input = get_input();
-    let input = parse_input(input);
-    println!(
-        "Running day {}:\n\tPart1 {}\n\tPart2 {}",
-        DAY,
-        part1(&input),
-        part2(&input)
-    );
+/// Round and cube rocks as one bit per cell, rows padded to whole words.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Dish {
+    pub width: usize,
+    pub height: usize,
+    /// Words per row.
+    stride: usize,
+    round: Vec<u64>,
+    cubes: Vec<u64>,
 }
 
-fn move_rocks(map: &Input) -> Input {
-    let mut after = map.clone();
-    for column in 0..map[0].len() {
-        let mut northest = 0;
-        for row in 0..map.len() {
-            match map[row][column] {
-                'O' => {
-                    after[row][column] = '.';
-                    after[northest][column] = 'O';
-                    northest += 1;
+impl Dish {
+    pub fn parse(input: &[String]) -> Self {
+        let height = input.len();
+        let width = input[0].len();
+        let stride = width.div_ceil(64);
+        let mut dish = Dish {
+            width,
+            height,
+            stride,
+            round: vec![0; height * stride],
+            cubes: vec![0; height * stride],
+        };
+        for (row, line) in input.iter().enumerate() {
+            for (column, c) in line.chars().enumerate() {
+                let (word, mask) = dish.index(row, column);
+                match c {
+                    'O' => dish.round[word] |= mask,
+                    '#' => dish.cubes[word] |= mask,
+                    '.' => (),
+                    c => panic!("Unexpected character: {}", c),
                 }
-                '#' => northest = row + 1,
-                _ => (),
             }
         }
+        dish
     }
-    after
-}
-
-fn compute_load(input: &Input) -> usize {
-    let y_size = input.len();
-    input
-        .iter()
-        .enumerate()
-        .flat_map(|(y, row)| {
-            row.iter()
-                .map(move |pos| if *pos == 'O' { y_size - y } else { 0 })
-        })
-        .sum()
-}
 
-fn part1(input: &Input) -> usize {
-    compute_load(&move_rocks(input))
-}
+    fn index(&self, row: usize, column: usize) -> (usize, u64) {
+        (row * self.stride + column / 64, 1 << (column % 64))
+    }
 
-fn rotate(input: &Input) -> Input {
-    let mut rotated = vec![vec!['.'; input.len()]; input[0].len()];
-    for column in 0..input[0].len() {
-        for row in 0..input.len() {
-            rotated[column][input[0].len() - 1 - row] = input[row][column];
+    /// Cell `offset` steps away from the wall the rocks roll towards, on row or column `line`.
+    fn cell(&self, tilt: Tilt, line: usize, offset: usize) -> (usize, usize) {
+        match tilt {
+            Tilt::North => (offset, line),
+            Tilt::South => (self.height - 1 - offset, line),
+            Tilt::West => (line, offset),
+            Tilt::East => (line, self.width - 1 - offset),
         }
     }
-    rotated
-}
 
-fn part2(input: &Input) -> usize {
-    let mut input = input.clone();
-    let mut memory = HashMap::new();
-    let mut cycle = 0;
-    loop {
-        cycle += 1;
-        for _ in 0..4 {
-            input = move_rocks(&input);
-            input = rotate(&input);
+    pub fn tilt(&mut self, tilt: Tilt) {
+        let (lines, length) = match tilt {
+            Tilt::North | Tilt::South => (self.width, self.height),
+            Tilt::West | Tilt::East => (self.height, self.width),
+        };
+        for line in 0..lines {
+            // Closest free cell a rolling rock would stop on
+            let mut stop = 0;
+            for offset in 0..length {
+                let (row, column) = self.cell(tilt, line, offset);
+                let (word, mask) = self.index(row, column);
+                if self.cubes[word] & mask != 0 {
+                    stop = offset + 1;
+                } else if self.round[word] & mask != 0 {
+                    self.round[word] &= !mask;
+                    let (row, column) = self.cell(tilt, line, stop);
+                    let (word, mask) = self.index(row, column);
+                    self.round[word] |= mask;
+                    stop += 1;
+                }
+            }
         }
-        if memory.contains_key(&input) {
-            break;
+    }
+
+    /// Tilts north, west, south and east in turn.
+    pub fn spin(&mut self) {
+        for tilt in [Tilt::North, Tilt::West, Tilt::South, Tilt::East] {
+            self.tilt(tilt);
         }
-        memory.insert(input.clone(), cycle);
     }
-    let cycle_start = *memory.get(&input).unwrap();
-    let cycle_length = cycle - cycle_start;
-    let final_cycle = (1_000_000_000 - cycle) % cycle_length;
-    for _ in 0..final_cycle {
-        for _ in 0..4 {
-            input = move_rocks(&input);
-            input = rotate(&input);
+
+    /// Load on the north support beams.
+    pub fn load(&self) -> usize {
+        self.round
+            .chunks(self.stride)
+            .enumerate()
+            .map(|(row, words)| {
+                let rocks = words.iter().map(|word| word.count_ones()).sum::<u32>();
+                (self.height - row) * rocks as usize
+            })
+            .sum()
+    }
+
+    /// Hash of the round rocks, cube rocks never move.
+    pub fn fingerprint(&self) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.round.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl fmt::Display for Dish {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.height {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            for column in 0..self.width {
+                let (word, mask) = self.index(row, column);
+                if self.round[word] & mask != 0 {
+                    write!(f, "O")?;
+                } else if self.cubes[word] & mask != 0 {
+                    write!(f, "#")?;
+                } else {
+                    write!(f, ".")?;
+                }
+            }
         }
+        Ok(())
     }
-    compute_load(&input)
+}
+
+fn parse_input(input: Vec<String>) -> Dish {
+    Dish::parse(&input)
+}
+
+pub fn run_day() {
+    let input = get_input();
+    let input = parse_input(input);
+    println!(
+        "Running day {}:\n\tPart1 {}\n\tPart2 {}",
+        DAY,
+        part1(&input),
+        part2(&input)
+    );
+}
+
+fn part1(input: &Dish) -> usize {
+    let mut dish = input.clone();
+    dish.tilt(Tilt::North);
+    dish.load()
+}
+
+fn part2(input: &Dish) -> usize {
+    cycle::hashed(input.clone(), Dish::spin)
+        .state_at(1_000_000_000, Dish::spin)
+        .load()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    const SAMPLE: &str = r"O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#....";
+
+    #[test]
+    fn part1_sample() {
+        let input = parse_input(sample_lines(SAMPLE));
+        assert_eq!(136, part1(&input));
+        assert_eq!(64, part2(&input));
+        let brent = cycle::brent(input.clone(), Dish::spin);
+        let hashed = cycle::hashed(input.clone(), Dish::spin);
+        assert_eq!((3, 7), (brent.prefix, brent.period));
+        assert_eq!((3, 7), (hashed.prefix, hashed.period));
+    }
+
+    #[test]
+    fn bitboard_tilts() {
+        let mut dish = parse_input(sample_lines(SAMPLE));
+        dish.spin();
+        assert_eq!(
+            r".....#....
+....#...O#
+...OO##...
+.OO#......
+.....OOO#.
+.O#...O#.#
+....O#....
+......OOOO
+#...O###..
+#..OO#....",
+            dish.to_string()
+        );
+        dish.tilt(Tilt::West);
+        assert!(dish.to_string().starts_with(".....#....\n....#O...#"));
+        assert_ne!(
+            parse_input(sample_lines(SAMPLE)).fingerprint(),
+            dish.fingerprint()
+        );
+
+        // Rows wider than a word
+        let wide = format!("{}O", ".".repeat(69));
+        let mut dish = parse_input(vec![wide.clone(), "#".repeat(70)]);
+        assert_eq!(wide, dish.to_string().lines().next().unwrap());
+        dish.tilt(Tilt::West);
+        assert_eq!(2, dish.load());
+        let mut round_trip = dish.clone();
+        round_trip.tilt(Tilt::East);
+        assert_eq!(wide, round_trip.to_string().lines().next().unwrap());
+    }
 
     #[test]
     fn part1_output() {
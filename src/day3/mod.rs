@@ -13,42 +13,58 @@ fn get_input() -> Vec<String> {
     reader.lines().collect::<Result<_, _>>().unwrap()
 }
 
-fn read_schematic(schematic: &[Vec<char>]) -> (Vec<Number>, Vec<Part>) {
+/// Column to start reading `row` at so that no number is cut in two. On a wrapping board a
+/// number can run off the row's end and continue at its start.
+fn row_start(row: &[char], rules: &Rules) -> usize {
+    if !rules.adjacency.wrapping {
+        return 0;
+    }
+    (0..row.len())
+        .find(|c| {
+            let before = row[(c + row.len() - 1) % row.len()];
+            let sign = rules.signed_numbers && matches!(before, '-' | '+');
+            let continues = before.is_ascii_digit() || sign && row[*c].is_ascii_digit();
+            !continues
+        })
+        .unwrap_or(0)
+}
+
+fn read_schematic(schematic: &[Vec<char>], rules: &Rules) -> (Vec<Number>, Vec<Part>) {
     let mut numbers = Vec::new();
     let mut parts = Vec::new();
 
     for (r, row) in schematic.iter().enumerate() {
-        let mut number = String::new();
-        let mut positions = HashSet::new();
-        for (c, col) in row.iter().enumerate() {
-            if col.is_ascii_digit() {
-                number.push(*col);
-                positions.insert((r, c));
+        let offset = row_start(row, rules);
+        let column = |c: usize| (offset + c) % row.len();
+        let cell = |c: usize| row[column(c)];
+        let mut c = 0;
+        while c < row.len() {
+            let start = c;
+            if rules.signed_numbers
+                && matches!(cell(c), '-' | '+')
+                && c + 1 < row.len()
+                && cell(c + 1).is_ascii_digit()
+            {
+                c += 1;
+            }
+            if cell(c).is_ascii_digit() {
+                while c < row.len() && cell(c).is_ascii_digit() {
+                    c += 1;
+                }
+                numbers.push(Number {
+                    num: (start..c).map(cell).collect::<String>().parse().unwrap(),
+                    positions: (start..c).map(|c| (r, column(c))).collect(),
+                });
             } else {
-                if *col != '.' {
+                if cell(c) != '.' {
                     parts.push(Part {
-                        symbol: *col,
-                        position: (r, c),
+                        symbol: cell(c),
+                        position: (r, column(c)),
                     });
                 }
-                if !number.is_empty() {
-                    numbers.push(Number {
-                        num: number.parse().unwrap(),
-                        positions: positions.clone(),
-                    });
-                    number.clear();
-                    positions.clear();
-                }
+                c += 1;
             }
         }
-        if !number.is_empty() {
-            numbers.push(Number {
-                num: number.parse().unwrap(),
-                positions: positions.clone(),
-            });
-            number.clear();
-            positions.clear();
-        }
     }
 
     (numbers, parts)
@@ -58,14 +74,15 @@ fn relate_part_numbers(
     numbers: &Vec<Number>,
     parts: &Vec<Part>,
     dimensions: Pos,
-) -> HashMap<Part, Vec<u32>> {
+    adjacency: &Adjacency,
+) -> HashMap<Part, Vec<i64>> {
     let mut part_mapping = HashMap::new();
     for part in parts {
         for number in numbers {
-            if part.is_part_number(number, dimensions) {
+            if part.is_part_number(number, dimensions, adjacency) {
                 part_mapping
                     .entry(*part)
-                    .and_modify(|m: &mut Vec<u32>| m.push(number.num))
+                    .and_modify(|m: &mut Vec<i64>| m.push(number.num))
                     .or_insert(vec![number.num]);
             }
         }
@@ -73,13 +90,22 @@ fn relate_part_numbers(
     part_mapping
 }
 
-fn parse_input(input: Vec<String>) -> HashMap<Part, Vec<u32>> {
+fn parse_input(input: Vec<String>) -> HashMap<Part, Vec<i64>> {
+    parse_input_with(input, &Rules::default())
+}
+
+pub fn parse_input_with(input: Vec<String>, rules: &Rules) -> HashMap<Part, Vec<i64>> {
     let input = input
         .iter()
         .map(|l| l.chars().collect())
         .collect::<Vec<Vec<char>>>();
-    let (numbers, parts) = read_schematic(&input);
-    relate_part_numbers(&numbers, &parts, (input.len(), input[0].len()))
+    let (numbers, parts) = read_schematic(&input, rules);
+    relate_part_numbers(
+        &numbers,
+        &parts,
+        (input.len(), input[0].len()),
+        &rules.adjacency,
+    )
 }
 
 pub fn run_day() {
@@ -92,23 +118,29 @@ pub fn run_day() {
         part2(&input)
     );
 }
-type Pos = (usize, usize);
+pub type Pos = (usize, usize);
 
-#[derive(Debug)]
-struct Number {
-    pub num: u32,
-    pub positions: HashSet<Pos>,
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Neighbourhood {
+    Orthogonal,
+    Diagonal,
+    #[default]
+    Full,
 }
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-struct Part {
-    symbol: char,
-    position: Pos,
+/// Which cells count as touching a part and whether the board wraps around its edges. On a
+/// wrapping board numbers also continue from the end of a row onto its start.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Adjacency {
+    pub neighbourhood: Neighbourhood,
+    pub wrapping: bool,
 }
 
-impl Part {
-    fn is_part_number(&self, number: &Number, dimensions: Pos) -> bool {
-        const OFFSETS: [(isize, isize); 8] = [
+impl Adjacency {
+    fn offsets(&self) -> &'static [(isize, isize)] {
+        const ORTHOGONAL: [(isize, isize); 4] = [(0, 1), (-1, 0), (1, 0), (0, -1)];
+        const DIAGONAL: [(isize, isize); 4] = [(-1, 1), (1, 1), (-1, -1), (1, -1)];
+        const FULL: [(isize, isize); 8] = [
             (-1, 1),
             (0, 1),
             (1, 1),
@@ -118,41 +150,163 @@ impl Part {
             (0, -1),
             (1, -1),
         ];
-        let mut adjacent = HashSet::new();
-        for offset in OFFSETS {
-            let next_row = self.position.0.checked_add_signed(offset.0);
-            let next_col = self.position.1.checked_add_signed(offset.1);
-            if next_row.is_some_and(|r| r < dimensions.0)
-                && next_col.is_some_and(|c| c < dimensions.1)
-            {
-                adjacent.insert((next_row.unwrap(), next_col.unwrap()));
-            }
+        match self.neighbourhood {
+            Neighbourhood::Orthogonal => &ORTHOGONAL,
+            Neighbourhood::Diagonal => &DIAGONAL,
+            Neighbourhood::Full => &FULL,
         }
-        !number.positions.is_disjoint(&adjacent)
+    }
+
+    fn neighbours(&self, position: Pos, dimensions: Pos) -> HashSet<Pos> {
+        self.offsets()
+            .iter()
+            .filter_map(|offset| {
+                if self.wrapping {
+                    Some((
+                        (position.0 as isize + offset.0).rem_euclid(dimensions.0 as isize) as usize,
+                        (position.1 as isize + offset.1).rem_euclid(dimensions.1 as isize) as usize,
+                    ))
+                } else {
+                    let next_row = position
+                        .0
+                        .checked_add_signed(offset.0)
+                        .filter(|r| *r < dimensions.0)?;
+                    let next_col = position
+                        .1
+                        .checked_add_signed(offset.1)
+                        .filter(|c| *c < dimensions.1)?;
+                    Some((next_row, next_col))
+                }
+            })
+            .collect()
     }
 }
 
-fn part1(input: &HashMap<Part, Vec<u32>>) -> u32 {
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Rules {
+    pub adjacency: Adjacency,
+    /// Treat a `-` or `+` directly in front of a digit as the number's sign instead of a part.
+    pub signed_numbers: bool,
+}
+
+#[derive(Debug)]
+struct Number {
+    pub num: i64,
+    pub positions: HashSet<Pos>,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Part {
+    pub symbol: char,
+    pub position: Pos,
+}
+
+impl Part {
+    fn is_part_number(&self, number: &Number, dimensions: Pos, adjacency: &Adjacency) -> bool {
+        !number
+            .positions
+            .is_disjoint(&adjacency.neighbours(self.position, dimensions))
+    }
+}
+
+fn part1(input: &HashMap<Part, Vec<i64>>) -> i64 {
     input
         .clone()
         .into_values()
-        .map(|nums| nums.iter().sum::<u32>())
+        .map(|nums| nums.iter().sum::<i64>())
         .sum()
 }
 
-fn part2(input: &HashMap<Part, Vec<u32>>) -> u32 {
+fn part2(input: &HashMap<Part, Vec<i64>>) -> i64 {
     input
         .keys()
         .filter(|k| k.symbol == '*')
         .map(|k| input.get(k).unwrap())
         .filter(|gear| gear.len() == 2)
-        .map(|gear| gear.iter().product::<u32>())
+        .map(|gear| gear.iter().product::<i64>())
         .sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    #[test]
+    fn part1_sample() {
+        let input = parse_input(sample_lines(
+            r"467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..
+",
+        ));
+        assert_eq!(4361, part1(&input));
+        assert_eq!(467835, part2(&input));
+    }
+
+    #[test]
+    fn signed_wrapping_sample() {
+        let rules = Rules {
+            adjacency: Adjacency {
+                neighbourhood: Neighbourhood::Orthogonal,
+                wrapping: true,
+            },
+            signed_numbers: true,
+        };
+        let input = parse_input_with(
+            sample_lines(
+                r"*..-12
+9.....
+......
+..-3..
++4*...
+",
+            ),
+            &rules,
+        );
+        assert_eq!(2, part1(&input));
+        assert_eq!(-12, part2(&input));
+    }
+
+    #[test]
+    fn diagonal_sample() {
+        let board = "1..\n.*5\n..4";
+        let with = |neighbourhood| Rules {
+            adjacency: Adjacency {
+                neighbourhood,
+                wrapping: false,
+            },
+            signed_numbers: false,
+        };
+        let input = parse_input_with(sample_lines(board), &with(Neighbourhood::Diagonal));
+        assert_eq!(5, part1(&input));
+        assert_eq!(4, part2(&input));
+        let input = parse_input_with(sample_lines(board), &with(Neighbourhood::Orthogonal));
+        assert_eq!(5, part1(&input));
+        assert_eq!(0, part2(&input));
+        let input = parse_input_with(sample_lines(board), &with(Neighbourhood::Full));
+        assert_eq!(10, part1(&input));
+    }
+
+    #[test]
+    fn numbers_wrap_around_rows() {
+        let mut rules = Rules::default();
+        rules.adjacency.wrapping = true;
+        let input = parse_input_with(sample_lines("3..12\n.*..."), &rules);
+        assert_eq!(123, part1(&input));
+        rules.signed_numbers = true;
+        let input = parse_input_with(sample_lines("2..-1\n*...."), &rules);
+        assert_eq!(-12, part1(&input));
+        let input = parse_input_with(sample_lines("3..12\n.*..."), &Rules::default());
+        assert_eq!(3, part1(&input));
+    }
 
     #[test]
     fn part1_output() {
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{fmt, str::FromStr};
 
 use aoc_downloader::download_day;
 
@@ -14,7 +14,7 @@ fn get_input() -> Vec<String> {
 }
 
 #[derive(Debug)]
-struct Game {
+pub struct Game {
     pub id: usize,
     winners: Vec<u64>,
     numbers: Vec<u64>,
@@ -73,15 +73,87 @@ impl Game {
             result
         }
     }
+}
 
-    fn is_winner(&self) -> bool {
-        self.winners
-            .iter()
-            .any(|w| self.numbers.iter().any(|n| n == w))
+/// Decides which of the following cards a card hands out copies of.
+#[derive(Clone, Copy, Debug)]
+pub struct WinRule {
+    /// Number of copies of each won card handed out per instance of the winning card.
+    pub copies_per_match: u64,
+    /// Distance between consecutive cards that receive a copy.
+    pub stride: usize,
+}
+
+impl Default for WinRule {
+    fn default() -> Self {
+        WinRule {
+            copies_per_match: 1,
+            stride: 1,
+        }
+    }
+}
+
+impl WinRule {
+    fn won_cards(&self, card: usize, matches: u64) -> impl Iterator<Item = usize> + '_ {
+        (1..=matches as usize).map(move |i| card + i * self.stride)
+    }
+}
+
+/// Result of playing out the scratchcards, with the origin of every copy.
+#[derive(Debug)]
+pub struct Cascade {
+    pub ids: Vec<usize>,
+    /// Number of instances held of each card, originals included.
+    pub copies: Vec<u64>,
+    /// For each card the cards it won, with how many copies it handed to each.
+    pub produced: Vec<Vec<(usize, u64)>>,
+}
+
+impl Cascade {
+    pub fn total(&self) -> u64 {
+        self.copies.iter().sum()
+    }
+}
+
+impl fmt::Display for Cascade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (card, id) in self.ids.iter().enumerate() {
+            write!(f, "Card {}: {} instance(s)", id, self.copies[card])?;
+            if !self.produced[card].is_empty() {
+                let won = self.produced[card]
+                    .iter()
+                    .map(|(target, count)| format!("{} x{}", self.ids[*target], count))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, " -> wins {}", won)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "Total: {}", self.total())
     }
 }
 
-fn parse_input(input: Vec<String>) -> Vec<Game> {
+pub fn simulate(cards: &[Game], rule: &WinRule) -> Cascade {
+    let mut copies = vec![1; cards.len()];
+    let mut produced = vec![Vec::new(); cards.len()];
+    for (card, game) in cards.iter().enumerate() {
+        for target in rule
+            .won_cards(card, game.get_matches())
+            .take_while(|target| *target < cards.len())
+        {
+            let won = copies[card] * rule.copies_per_match;
+            copies[target] += won;
+            produced[card].push((target, won));
+        }
+    }
+    Cascade {
+        ids: cards.iter().map(|game| game.id).collect(),
+        copies,
+        produced,
+    }
+}
+
+pub fn parse_input(input: Vec<String>) -> Vec<Game> {
     input
         .iter()
         .map(|line| Game::from_str(line).unwrap())
@@ -103,36 +175,51 @@ fn part1(input: &[Game]) -> u64 {
     input.iter().map(|g| g.get_score()).sum()
 }
 
-fn count_winning_cards(card: usize, winner_cards: &HashMap<usize, u64>) -> u64 {
-    if !winner_cards.contains_key(&card) {
-        return 1;
-    }
-    let mut card_count = 1;
-    for c in (card + 1)..=(card + *winner_cards.get(&card).unwrap() as usize) {
-        card_count += count_winning_cards(c, winner_cards)
-    }
-    card_count
-}
-
 fn part2(input: &[Game]) -> u64 {
-    let mut winning_cards = HashMap::new();
-    input
-        .iter()
-        .filter(|card| card.is_winner())
-        .for_each(|card| {
-            winning_cards.insert(card.id, card.get_matches());
-        });
-    winning_cards
-        .keys()
-        .map(|card| count_winning_cards(*card, &winning_cards))
-        .sum::<u64>()
-        + input.len() as u64
-        - winning_cards.len() as u64
+    simulate(input, &WinRule::default()).total()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    const SAMPLE: &str = r"Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
+";
+
+    #[test]
+    fn part2_sample() {
+        let input = parse_input(sample_lines(SAMPLE));
+        let cascade = simulate(&input, &WinRule::default());
+        assert_eq!(vec![1, 2, 4, 8, 14, 1], cascade.copies);
+        assert_eq!(vec![(2, 2), (3, 2)], cascade.produced[1]);
+        assert_eq!(30, part2(&input));
+    }
+
+    #[test]
+    fn scaled_rule_sample() {
+        let input = parse_input(sample_lines(SAMPLE));
+        let rule = WinRule {
+            copies_per_match: 1,
+            stride: 2,
+        };
+        let cascade = simulate(&input, &rule);
+        assert_eq!(vec![1, 1, 2, 2, 4, 4], cascade.copies);
+        let rule = WinRule {
+            copies_per_match: 2,
+            stride: 1,
+        };
+        let cascade = simulate(&input, &rule);
+        assert_eq!(vec![1, 3, 9, 27, 75, 1], cascade.copies);
+        assert_eq!(vec![(1, 2), (2, 2), (3, 2), (4, 2)], cascade.produced[0]);
+        assert_eq!(vec![(2, 6), (3, 6)], cascade.produced[1]);
+        assert_eq!(116, cascade.total());
+    }
 
     #[test]
     fn part1_output() {
@@ -1,5 +1,8 @@
-use rayon::prelude::*;
-use std::{fmt::Display, str::FromStr};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    str::FromStr,
+};
 
 use aoc_downloader::download_day;
 
@@ -14,60 +17,35 @@ fn get_input() -> Vec<String> {
     reader.lines().collect::<Result<_, _>>().unwrap()
 }
 
-fn extract_mapping_to_(header: &str, kind: Mapping, input: &[String]) -> Vec<Range> {
-    input
-        .iter()
-        .skip_while(|line| line != &header)
-        .skip(1)
-        .take_while(|line| !line.is_empty())
-        .map(|line| Range::from_string(line, kind))
-        .collect()
-}
-
-fn parse_input(input: Vec<String>) -> (Vec<Seed>, Vec<Range>) {
-    let seeds: Vec<Seed> = input[0]
+pub fn parse_input(input: Vec<String>) -> Almanac {
+    let header_re = regex!(r"^(\w+)-to-(\w+) map:$");
+    let seeds = input[0]
         .split(' ')
         .skip(1)
         .map(|s| Seed::from_str(s).unwrap())
         .collect();
 
-    let mut mapping = extract_mapping_to_("seed-to-soil map:", Mapping::ToSoil, &input);
-    mapping.append(&mut extract_mapping_to_(
-        "soil-to-fertilizer map:",
-        Mapping::ToFertilizer,
-        &input,
-    ));
-    mapping.append(&mut extract_mapping_to_(
-        "fertilizer-to-water map:",
-        Mapping::ToWater,
-        &input,
-    ));
-    mapping.append(&mut extract_mapping_to_(
-        "water-to-light map:",
-        Mapping::ToLight,
-        &input,
-    ));
-    mapping.append(&mut extract_mapping_to_(
-        "light-to-temperature map:",
-        Mapping::ToTemperature,
-        &input,
-    ));
-    mapping.append(&mut extract_mapping_to_(
-        "temperature-to-humidity map:",
-        Mapping::ToHumidity,
-        &input,
-    ));
-    mapping.append(&mut extract_mapping_to_(
-        "humidity-to-location map:",
-        Mapping::ToLocation,
-        &input,
-    ));
-
-    (seeds, mapping)
+    let mut maps: Vec<CategoryMap> = Vec::new();
+    for line in input.iter().skip(1).filter(|line| !line.is_empty()) {
+        if let Some(header) = header_re.captures(line) {
+            maps.push(CategoryMap {
+                from: header[1].to_string(),
+                to: header[2].to_string(),
+                ranges: Vec::new(),
+            });
+        } else {
+            maps.last_mut()
+                .unwrap_or_else(|| panic!("Range outside of a map: {}", line))
+                .ranges
+                .push(Range::from_string(line));
+        }
+    }
+
+    Almanac { seeds, maps }
 }
 
 #[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash, Debug)]
-struct Seed(usize);
+pub struct Seed(pub usize);
 
 impl Display for Seed {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -85,25 +63,69 @@ impl FromStr for Seed {
     }
 }
 
+/// Half-open interval `[start, end)` of values within one category.
+#[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Debug)]
+pub struct Interval {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Interval {
+    fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+}
+
+fn merge_intervals(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.sort();
+    let mut merged: Vec<Interval> = Vec::with_capacity(intervals.len());
+    for interval in intervals.into_iter().filter(|i| !i.is_empty()) {
+        match merged.last_mut() {
+            Some(last) if interval.start <= last.end => last.end = last.end.max(interval.end),
+            _ => merged.push(interval),
+        }
+    }
+    merged
+}
+
 #[derive(Debug)]
-struct Range {
+pub struct Range {
     pub destination: usize,
     pub source: usize,
     pub length: usize,
-    pub kind: Mapping,
 }
 
 impl Range {
-    fn seed_in_range(&self, seed: &Seed) -> bool {
-        seed.0 >= self.source && seed.0 < (self.source + self.length)
-    }
-
-    fn map_seed(&self, s: &mut Seed) {
-        let offset = s.0 - self.source;
-        *s = Seed(self.destination + offset);
+    /// Splits `interval` into the mapped part covered by this range and the uncovered rest.
+    fn map_interval(&self, interval: Interval) -> (Option<Interval>, Vec<Interval>) {
+        let overlap = Interval {
+            start: interval.start.max(self.source),
+            end: interval.end.min(self.source + self.length),
+        };
+        if overlap.is_empty() {
+            return (None, vec![interval]);
+        }
+        let mapped = Interval {
+            start: overlap.start - self.source + self.destination,
+            end: overlap.end - self.source + self.destination,
+        };
+        let rest = [
+            Interval {
+                start: interval.start,
+                end: overlap.start,
+            },
+            Interval {
+                start: overlap.end,
+                end: interval.end,
+            },
+        ]
+        .into_iter()
+        .filter(|i| !i.is_empty())
+        .collect();
+        (Some(mapped), rest)
     }
 
-    fn from_string(s: &str, kind: Mapping) -> Self {
+    fn from_string(s: &str) -> Self {
         let values: Vec<usize> = s
             .split(' ')
             .map(|num| {
@@ -115,21 +137,248 @@ impl Range {
             destination: values[0],
             source: values[1],
             length: values[2],
-            kind,
         }
     }
 }
 
-#[allow(clippy::enum_variant_names)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum Mapping {
-    ToSoil,
-    ToFertilizer,
-    ToWater,
-    ToLight,
-    ToTemperature,
-    ToHumidity,
-    ToLocation,
+/// All ranges converting values of one category into another.
+#[derive(Debug)]
+pub struct CategoryMap {
+    pub from: String,
+    pub to: String,
+    pub ranges: Vec<Range>,
+}
+
+impl CategoryMap {
+    fn apply(&self, intervals: Vec<Interval>) -> Vec<Interval> {
+        let mut mapped = Vec::new();
+        let mut unmapped = intervals;
+        for range in self.ranges.iter() {
+            let mut rest = Vec::new();
+            for interval in unmapped {
+                let (inside, mut outside) = range.map_interval(interval);
+                mapped.extend(inside);
+                rest.append(&mut outside);
+            }
+            unmapped = rest;
+        }
+        mapped.append(&mut unmapped);
+        merge_intervals(mapped)
+    }
+
+    /// This map as contiguous pieces covering every value, unmapped gaps included.
+    fn to_piecewise(&self) -> PiecewiseMap {
+        let mut ranges = self.ranges.iter().collect::<Vec<_>>();
+        ranges.sort_by_key(|range| range.source);
+        let mut pieces = Vec::new();
+        let mut next = 0;
+        for range in ranges {
+            let start = range.source.max(next);
+            let end = range.source + range.length;
+            if start >= end {
+                continue;
+            }
+            pieces.push(Piece::identity(next, start));
+            pieces.push(Piece {
+                source: Interval { start, end },
+                offset: range.destination as i64 - range.source as i64,
+            });
+            next = end;
+        }
+        pieces.push(Piece::identity(next, usize::MAX));
+        PiecewiseMap::new(pieces)
+    }
+}
+
+/// Values in `source` are shifted by `offset`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Piece {
+    pub source: Interval,
+    pub offset: i64,
+}
+
+impl Piece {
+    fn identity(start: usize, end: usize) -> Self {
+        Piece {
+            source: Interval { start, end },
+            offset: 0,
+        }
+    }
+
+    fn shift(value: usize, offset: i64) -> usize {
+        (value as i64 + offset) as usize
+    }
+
+    fn image(&self) -> Interval {
+        if self.offset == 0 {
+            return self.source;
+        }
+        Interval {
+            start: Piece::shift(self.source.start, self.offset),
+            end: Piece::shift(self.source.end, self.offset),
+        }
+    }
+}
+
+/// A chain of category maps collapsed into a single piecewise-linear function.
+#[derive(Clone, Debug)]
+pub struct PiecewiseMap {
+    /// Sorted, contiguous pieces covering `[0, usize::MAX)`.
+    pub pieces: Vec<Piece>,
+}
+
+impl PiecewiseMap {
+    fn new(pieces: Vec<Piece>) -> Self {
+        let mut merged: Vec<Piece> = Vec::with_capacity(pieces.len());
+        for piece in pieces.into_iter().filter(|p| !p.source.is_empty()) {
+            match merged.last_mut() {
+                Some(last) if last.offset == piece.offset => last.source.end = piece.source.end,
+                _ => merged.push(piece),
+            }
+        }
+        PiecewiseMap { pieces: merged }
+    }
+
+    pub fn apply(&self, value: usize) -> usize {
+        let piece = self.pieces[self
+            .pieces
+            .partition_point(|piece| piece.source.end <= value)];
+        Piece::shift(value, piece.offset)
+    }
+
+    /// Function applying `self` first and `other` to its result.
+    pub fn then(&self, other: &PiecewiseMap) -> PiecewiseMap {
+        let mut pieces = Vec::new();
+        for piece in self.pieces.iter() {
+            let image = piece.image();
+            for next in other.pieces.iter() {
+                let start = image.start.max(next.source.start);
+                let end = image.end.min(next.source.end);
+                if start < end {
+                    pieces.push(Piece {
+                        source: Interval {
+                            start: Piece::shift(start, -piece.offset),
+                            end: Piece::shift(end, -piece.offset),
+                        },
+                        offset: piece.offset + next.offset,
+                    });
+                }
+            }
+        }
+        pieces.sort_by_key(|piece| piece.source);
+        PiecewiseMap::new(pieces)
+    }
+
+    /// All inputs whose output lies in `target`.
+    pub fn preimage(&self, target: Interval) -> Vec<Interval> {
+        merge_intervals(
+            self.pieces
+                .iter()
+                .filter_map(|piece| {
+                    let image = piece.image();
+                    let start = image.start.max(target.start);
+                    let end = image.end.min(target.end);
+                    (start < end).then(|| Interval {
+                        start: Piece::shift(start, -piece.offset),
+                        end: Piece::shift(end, -piece.offset),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Input from `domain` with the smallest output, together with that output.
+    pub fn minimum(&self, domain: &[Interval]) -> Option<(usize, usize)> {
+        self.pieces
+            .iter()
+            .flat_map(|piece| {
+                domain.iter().filter_map(|interval| {
+                    let start = interval.start.max(piece.source.start);
+                    (start < interval.end.min(piece.source.end))
+                        .then(|| (start, Piece::shift(start, piece.offset)))
+                })
+            })
+            .min_by_key(|(_, output)| *output)
+    }
+}
+
+impl Display for PiecewiseMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for piece in self.pieces.iter() {
+            let image = piece.image();
+            writeln!(
+                f,
+                "[{}, {}) -> [{}, {}) ({:+})",
+                piece.source.start, piece.source.end, image.start, image.end, piece.offset
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Almanac {
+    pub seeds: Vec<Seed>,
+    pub maps: Vec<CategoryMap>,
+}
+
+impl Almanac {
+    /// Chain of maps leading from category `from` to category `to`, if the two are connected.
+    pub fn path(&self, from: &str, to: &str) -> Option<Vec<&CategoryMap>> {
+        let mut previous: HashMap<&str, &CategoryMap> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(category) = queue.pop_front() {
+            if category == to {
+                let mut path = Vec::new();
+                let mut current = to;
+                while current != from {
+                    let map = previous[current];
+                    path.push(map);
+                    current = &map.from;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for map in self.maps.iter().filter(|map| map.from == category) {
+                if map.to != from && !previous.contains_key(map.to.as_str()) {
+                    previous.insert(&map.to, map);
+                    queue.push_back(&map.to);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn convert_intervals(
+        &self,
+        from: &str,
+        to: &str,
+        intervals: Vec<Interval>,
+    ) -> Option<Vec<Interval>> {
+        Some(
+            self.path(from, to)?
+                .iter()
+                .fold(merge_intervals(intervals), |intervals, map| {
+                    map.apply(intervals)
+                }),
+        )
+    }
+
+    pub fn convert(&self, from: &str, to: &str, value: usize) -> Option<usize> {
+        let interval = Interval {
+            start: value,
+            end: value + 1,
+        };
+        Some(self.convert_intervals(from, to, vec![interval])?[0].start)
+    }
+
+    /// Every map on the path from `from` to `to` folded into one function.
+    pub fn compose(&self, from: &str, to: &str) -> Option<PiecewiseMap> {
+        Some(self.path(from, to)?.iter().fold(
+            PiecewiseMap::new(vec![Piece::identity(0, usize::MAX)]),
+            |composed, map| composed.then(&map.to_piecewise()),
+        ))
+    }
 }
 
 pub fn run_day() {
@@ -143,63 +392,124 @@ pub fn run_day() {
     );
 }
 
-fn apply_map(seeds: &mut Vec<Seed>, mappings: &[Range], kind: Mapping) {
-    seeds.par_iter_mut().for_each(|s| {
-        for mapping in mappings.iter() {
-            if mapping.kind == kind && mapping.seed_in_range(s) {
-                return mapping.map_seed(s);
-            }
-        }
-    });
+fn lowest_location(almanac: &Almanac, seeds: Vec<Interval>) -> Seed {
+    let locations = almanac
+        .convert_intervals("seed", "location", seeds)
+        .expect("No path from seed to location");
+    Seed(locations[0].start)
 }
 
-fn part1(input: &(Vec<Seed>, Vec<Range>)) -> Seed {
-    let (seeds, mappings) = input;
-    let mut seeds = seeds.clone();
-    apply_map(&mut seeds, mappings, Mapping::ToSoil);
-    apply_map(&mut seeds, mappings, Mapping::ToFertilizer);
-    apply_map(&mut seeds, mappings, Mapping::ToWater);
-    apply_map(&mut seeds, mappings, Mapping::ToLight);
-    apply_map(&mut seeds, mappings, Mapping::ToTemperature);
-    apply_map(&mut seeds, mappings, Mapping::ToHumidity);
-    apply_map(&mut seeds, mappings, Mapping::ToLocation);
-
-    *seeds.iter().min().unwrap()
+fn part1(input: &Almanac) -> Seed {
+    let seeds = input
+        .seeds
+        .iter()
+        .map(|seed| Interval {
+            start: seed.0,
+            end: seed.0 + 1,
+        })
+        .collect();
+    lowest_location(input, seeds)
 }
 
-fn part2(input: &(Vec<Seed>, Vec<Range>)) -> Seed {
-    let (seeds, mappings) = input;
-    let mut seeds = seeds
+fn part2(input: &Almanac) -> Seed {
+    let seeds = input
+        .seeds
         .chunks(2)
-        .flat_map(|chunk| {
-            ((chunk[0].0)..(chunk[0].0 + chunk[1].0))
-                .map(Seed)
-                .collect::<Vec<Seed>>()
+        .map(|chunk| Interval {
+            start: chunk[0].0,
+            end: chunk[0].0 + chunk[1].0,
         })
         .collect();
-    apply_map(&mut seeds, mappings, Mapping::ToSoil);
-    apply_map(&mut seeds, mappings, Mapping::ToFertilizer);
-    apply_map(&mut seeds, mappings, Mapping::ToWater);
-    apply_map(&mut seeds, mappings, Mapping::ToLight);
-    apply_map(&mut seeds, mappings, Mapping::ToTemperature);
-    apply_map(&mut seeds, mappings, Mapping::ToHumidity);
-    apply_map(&mut seeds, mappings, Mapping::ToLocation);
-
-    *seeds.iter().min().unwrap()
+    lowest_location(input, seeds)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    #[test]
+    fn sample() {
+        let input = parse_input(sample_lines(SAMPLE));
+        assert_eq!(Seed(35), part1(&input));
+        assert_eq!(Seed(46), part2(&input));
+    }
+
+    #[test]
+    fn category_queries() {
+        let input = parse_input(sample_lines(SAMPLE));
+        assert_eq!(Some(81), input.convert("seed", "water", 79));
+        assert_eq!(Some(53), input.convert("soil", "fertilizer", 14));
+        assert_eq!(None, input.convert("location", "seed", 82));
+        let close_seeds = input
+            .seeds
+            .iter()
+            .filter(|seed| input.convert("seed", "location", seed.0).unwrap() < 50)
+            .copied()
+            .collect::<Vec<Seed>>();
+        assert_eq!(vec![Seed(14), Seed(13)], close_seeds);
+    }
+
+    #[test]
+    fn composed_mapping() {
+        let input = parse_input(sample_lines(SAMPLE));
+        let composed = input.compose("seed", "location").unwrap();
+        for seed in 0..110 {
+            assert_eq!(
+                input.convert("seed", "location", seed),
+                Some(composed.apply(seed))
+            );
+        }
+        let seeds = [
+            Interval { start: 79, end: 93 },
+            Interval { start: 55, end: 68 },
+        ];
+        assert_eq!(Some((82, 46)), composed.minimum(&seeds));
+        let candidates = composed.preimage(Interval { start: 46, end: 47 });
+        assert_eq!(vec![Interval { start: 82, end: 83 }], candidates);
+    }
+
+    const SAMPLE: &str = r"seeds: 79 14 55 13
+
+seed-to-soil map:
+50 98 2
+52 50 48
+
+soil-to-fertilizer map:
+0 15 37
+37 52 2
+39 0 15
+
+fertilizer-to-water map:
+49 53 8
+0 11 42
+42 0 7
+57 7 4
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+45 77 23
+81 45 19
+68 64 13
+
+temperature-to-humidity map:
+0 69 1
+1 0 69
+
+humidity-to-location map:
+60 56 37
+56 93 4
+";
 
-    #[ignore]
     #[test]
     fn part1_output() {
         let input = parse_input(get_input());
         assert_eq!(Seed(107430936), part1(&input));
     }
 
-    #[ignore]
     #[test]
     fn part2_output() {
         let input = parse_input(get_input());
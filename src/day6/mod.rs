@@ -1,4 +1,5 @@
-use std::iter::zip;
+use num::{BigInt, ToPrimitive, Zero};
+use std::{fmt, iter::zip};
 
 use aoc_downloader::download_day;
 
@@ -13,34 +14,130 @@ fn get_input() -> Vec<String> {
     reader.lines().collect::<Result<_, _>>().unwrap()
 }
 
-#[derive(Clone, Copy, Debug)]
-struct Race {
-    pub duration: i64,
-    pub record: i64,
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Race {
+    pub duration: i128,
+    pub record: i128,
 }
 
 impl Race {
-    fn discriminat(&self) -> i64 {
-        // D = t^2 - 4 * -1 * -d
-        self.duration.pow(2) - 4 * self.record
+    /// Computed in `BigInt`, the product outgrows `i128` long before the duration does.
+    fn distance(&self, hold: i128) -> BigInt {
+        BigInt::from(hold) * (self.duration - hold)
     }
 
-    fn get_roots(&self) -> (Option<i64>, Option<i64>) {
-        let discriminat = self.discriminat();
-        if discriminat == 0 {
-            // x = -t + sqrt(D)
-            return (Some(-self.duration + discriminat.isqrt()), None);
+    fn beats_record(&self, hold: i128) -> bool {
+        self.distance(hold) > BigInt::from(self.record)
+    }
+
+    /// Shortest and longest hold times that beat the record, if any do.
+    pub fn winning_holds(&self) -> Option<(i128, i128)> {
+        // hold * (t - hold) > d <=> hold^2 - t * hold + d < 0
+        let duration = BigInt::from(self.duration);
+        let discriminant: BigInt = &duration * &duration - 4 * BigInt::from(self.record);
+        if discriminant <= BigInt::zero() {
+            return None;
+        }
+        // sqrt rounds down, so walk the estimate onto the first winning hold
+        let estimate: BigInt = (duration - discriminant.sqrt()) / 2;
+        let mut shortest = estimate
+            .to_i128()
+            .expect("Estimate lies within the race duration");
+        while shortest <= self.duration / 2 && !self.beats_record(shortest) {
+            shortest += 1;
+        }
+        while shortest > 0 && self.beats_record(shortest - 1) {
+            shortest -= 1;
+        }
+        let longest = self.duration - shortest;
+        (shortest <= longest && self.beats_record(shortest)).then_some((shortest, longest))
+    }
+
+    pub fn ways_to_win(&self) -> i128 {
+        self.winning_holds()
+            .map_or(0, |(shortest, longest)| longest - shortest + 1)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SheetError {
+    MissingLine(&'static str),
+    InvalidNumber(String),
+    ColumnMismatch { times: usize, distances: usize },
+}
+
+impl fmt::Display for SheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SheetError::MissingLine(label) => write!(f, "No line starting with '{}'", label),
+            SheetError::InvalidNumber(value) => write!(f, "Can't parse: {}", value),
+            SheetError::ColumnMismatch { times, distances } => {
+                write!(f, "{} times but {} distances", times, distances)
+            }
         }
-        // x = (-t + sqrt(D))/-2 & x = (-v - sqrt(D))/-2
-        (
-            Some(((self.duration as f64 - (discriminat as f64).sqrt()) / 2.).floor() as i64 + 1),
-            Some(((self.duration as f64 + (discriminat as f64).sqrt()) / 2.).ceil() as i64 - 1),
-        )
     }
 }
 
-fn parse_input(input: Vec<String>) -> Vec<String> {
-    input
+fn sheet_line<'a>(input: &'a [String], label: &'static str) -> Result<Vec<&'a str>, SheetError> {
+    let values = input
+        .iter()
+        .find_map(|line| line.strip_prefix(label))
+        .ok_or(SheetError::MissingLine(label))?
+        .split_whitespace()
+        .collect::<Vec<_>>();
+    match values
+        .iter()
+        .find(|value| !value.chars().all(|c| c.is_ascii_digit()))
+    {
+        Some(value) => Err(SheetError::InvalidNumber(value.to_string())),
+        None => Ok(values),
+    }
+}
+
+fn sheet_columns(input: &[String]) -> Result<(Vec<&str>, Vec<&str>), SheetError> {
+    let times = sheet_line(input, "Time:")?;
+    let distances = sheet_line(input, "Distance:")?;
+    if times.is_empty() || times.len() != distances.len() {
+        return Err(SheetError::ColumnMismatch {
+            times: times.len(),
+            distances: distances.len(),
+        });
+    }
+    Ok((times, distances))
+}
+
+fn parse_number(value: &str) -> Result<i128, SheetError> {
+    value
+        .parse()
+        .map_err(|_| SheetError::InvalidNumber(value.to_string()))
+}
+
+/// Reads every column of the sheet as its own race.
+pub fn parse_races(input: &[String]) -> Result<Vec<Race>, SheetError> {
+    let (times, distances) = sheet_columns(input)?;
+    zip(times, distances)
+        .map(|(duration, record)| {
+            Ok(Race {
+                duration: parse_number(duration)?,
+                record: parse_number(record)?,
+            })
+        })
+        .collect()
+}
+
+/// Reads the sheet as one race, ignoring the spaces between the columns.
+pub fn parse_kerned_race(input: &[String]) -> Result<Race, SheetError> {
+    let (times, distances) = sheet_columns(input)?;
+    Ok(Race {
+        duration: parse_number(&times.concat())?,
+        record: parse_number(&distances.concat())?,
+    })
+}
+
+fn parse_input(input: Vec<String>) -> (Vec<Race>, Race) {
+    let races = parse_races(&input).unwrap_or_else(|e| panic!("{}", e));
+    let kerned = parse_kerned_race(&input).unwrap_or_else(|e| panic!("{}", e));
+    (races, kerned)
 }
 
 pub fn run_day() {
@@ -54,48 +151,68 @@ pub fn run_day() {
     );
 }
 
-fn part1(input: &[String]) -> i64 {
-    let times = input[0]
-        .split(' ')
-        .filter_map(|split| split.parse().ok())
-        .collect::<Vec<i64>>();
-    let distances = input[1]
-        .split(' ')
-        .filter_map(|split| split.parse().ok())
-        .collect::<Vec<i64>>();
-    zip(times, distances)
-        .map(|(duration, record)| Race { duration, record })
-        .map(|race| race.get_roots())
-        .map(|(start, end)| end.unwrap() - start.unwrap() + 1)
-        .product()
+fn part1(input: &(Vec<Race>, Race)) -> i128 {
+    input.0.iter().map(|race| race.ways_to_win()).product()
 }
 
-fn part2(input: &[String]) -> i64 {
-    let roots = Race {
-        duration: input[0]
-            .split_whitespace()
-            .collect::<String>()
-            .split(':')
-            .last()
-            .unwrap()
-            .parse()
-            .unwrap(),
-        record: input[1]
-            .split_whitespace()
-            .collect::<String>()
-            .split(':')
-            .last()
-            .unwrap()
-            .parse()
-            .unwrap(),
-    }
-    .get_roots();
-    roots.1.unwrap() - roots.0.unwrap() + 1
+fn part2(input: &(Vec<Race>, Race)) -> i128 {
+    input.1.ways_to_win()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    #[test]
+    fn winning_holds() {
+        let race = |duration, record| Race { duration, record };
+        assert_eq!(Some((2, 5)), race(7, 9).winning_holds());
+        assert_eq!(Some((11, 19)), race(30, 200).winning_holds());
+        assert_eq!(None, race(4, 4).winning_holds());
+        assert_eq!(71503, race(71530, 940200).ways_to_win());
+        // One short of the perfect square record: only the midpoint wins
+        let large = 2 * 10_i128.pow(18);
+        assert_eq!(
+            Some((large / 2, large / 2)),
+            race(large, (large / 2).pow(2) - 1).winning_holds()
+        );
+        // Squaring this duration no longer fits an i128
+        let kerned =
+            parse_kerned_race(&sample_lines("Time: 200000000000000000000\nDistance: 1")).unwrap();
+        assert_eq!(199_999_999_999_999_999_999, kerned.ways_to_win());
+    }
+
+    #[test]
+    fn parse_sheet() {
+        let input = parse_input(sample_lines(
+            r"Time:      7  15   30
+Distance:  9  40  200
+",
+        ));
+        assert_eq!(3, input.0.len());
+        assert_eq!(288, part1(&input));
+        assert_eq!(71503, part2(&input));
+    }
+
+    #[test]
+    fn reject_bad_sheets() {
+        assert_eq!(
+            Err(SheetError::ColumnMismatch {
+                times: 3,
+                distances: 2
+            }),
+            parse_races(&sample_lines("Time: 7 15 30\nDistance: 9 40"))
+        );
+        assert_eq!(
+            Err(SheetError::InvalidNumber("4x".to_string())),
+            parse_races(&sample_lines("Time: 7\nDistance: 4x"))
+        );
+        assert_eq!(
+            Err(SheetError::MissingLine("Distance:")),
+            parse_kerned_race(&sample_lines("Time: 7"))
+        );
+    }
 
     #[test]
     fn part1_output() {
@@ -1,6 +1,7 @@
-use std::cmp::Ordering;
-use std::collections::BTreeMap;
-use std::str::FromStr;
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashMap;
+use std::fmt;
+use std::iter::zip;
 
 use aoc_downloader::download_day;
 
@@ -15,95 +16,172 @@ fn get_input() -> Vec<String> {
     reader.lines().collect::<Result<_, _>>().unwrap()
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-enum Card {
-    Joker,
-    Number(u64),
-    Jack,
-    Queen,
-    King,
-    Ace,
-}
-
-impl From<char> for Card {
-    fn from(value: char) -> Self {
-        match value {
-            'A' => Self::Ace,
-            'K' => Self::King,
-            'Q' => Self::Queen,
-            'J' => Self::Jack,
-            'T' => Self::Number(10),
-            'X' => Self::Joker,
-            e => {
-                if e.is_ascii_digit() {
-                    Self::Number(e.to_digit(10).unwrap() as u64)
-                } else {
-                    panic!("Unkown card: {}", e);
-                }
-            }
+/// A hand type, described by the card groups a hand needs to contain.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Type {
+    pub name: String,
+    /// Group sizes, largest first.
+    pub pattern: Vec<usize>,
+}
+
+impl Type {
+    pub fn new(name: &str, pattern: &[usize]) -> Self {
+        Type {
+            name: name.to_string(),
+            pattern: pattern.to_vec(),
         }
     }
+
+    /// Whether `groups` (largest first) can form this type with the help of `wildcards`, which
+    /// can only start new groups as one of the `spare_labels` not in the hand yet.
+    fn fits(&self, groups: &[usize], wildcards: usize, spare_labels: usize) -> bool {
+        let missing = self
+            .pattern
+            .iter()
+            .enumerate()
+            .map(|(i, size)| size.saturating_sub(groups.get(i).copied().unwrap_or(0)))
+            .sum::<usize>();
+        let new_groups = self.pattern.len().saturating_sub(groups.len());
+        missing <= wildcards && new_groups <= spare_labels
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
-enum Type {
-    HighCard(Vec<Card>),
-    OnePair(Vec<Card>),
-    TwoPair(Vec<Card>),
-    ThreeOfAKind(Vec<Card>),
-    FullHouse(Vec<Card>),
-    FourOfAKind(Vec<Card>),
-    FiveOfAKind(Vec<Card>),
+/// The rules of one variant of Camel Cards.
+#[derive(Clone, Debug)]
+pub struct Ruleset {
+    /// Card labels, weakest first.
+    pub order: Vec<char>,
+    /// Labels that act as whichever card makes the hand strongest.
+    pub wildcards: Vec<char>,
+    pub hand_size: usize,
+    /// Hand types, weakest first. A hand takes the strongest type it fits.
+    pub ranking: Vec<Type>,
 }
 
-impl Type {
-    fn from_cards(cards: &[Card]) -> Self {
-        let mut card_frequency = BTreeMap::new();
-        cards.iter().for_each(|c| {
-            card_frequency
-                .entry(c)
-                .and_modify(|v| {
-                    *v += 1;
-                })
-                .or_insert(1);
-        });
-        let jokers = if let Some(j) = card_frequency.get(&Card::Joker) {
-            *j
-        } else {
-            0
-        };
-        card_frequency.remove(&Card::Joker);
-        let max_count = card_frequency
+impl Ruleset {
+    fn standard_ranking() -> Vec<Type> {
+        vec![
+            Type::new("High card", &[1]),
+            Type::new("One pair", &[2]),
+            Type::new("Two pair", &[2, 2]),
+            Type::new("Three of a kind", &[3]),
+            Type::new("Full house", &[3, 2]),
+            Type::new("Four of a kind", &[4]),
+            Type::new("Five of a kind", &[5]),
+        ]
+    }
+
+    pub fn classic() -> Self {
+        Ruleset {
+            order: "23456789TJQKA".chars().collect(),
+            wildcards: Vec::new(),
+            hand_size: 5,
+            ranking: Ruleset::standard_ranking(),
+        }
+    }
+
+    pub fn jokers() -> Self {
+        Ruleset {
+            order: "J23456789TQKA".chars().collect(),
+            wildcards: vec!['J'],
+            hand_size: 5,
+            ranking: Ruleset::standard_ranking(),
+        }
+    }
+
+    fn strength(&self, card: char) -> usize {
+        self.order
             .iter()
-            .max_by_key(|(_, v)| *v)
-            .map(|(_, v)| *v)
-            .unwrap_or(0)
-            + jokers;
-        let different_cards = card_frequency.keys().count();
-
-        // max_count - different_cards is specific for each hand, to prevent negative numbers a
-        // offset of 4 is introduced
-        match 4 + max_count - different_cards {
-            0 => Self::HighCard(cards.to_vec()),
-            2 => Self::OnePair(cards.to_vec()),
-            3 => Self::TwoPair(cards.to_vec()),
-            4 => Self::ThreeOfAKind(cards.to_vec()),
-            5 => Self::FullHouse(cards.to_vec()),
-            6 => Self::FourOfAKind(cards.to_vec()),
-            _ => Self::FiveOfAKind(cards.to_vec()),
+            .position(|c| *c == card)
+            .unwrap_or_else(|| panic!("Unkown card: {}", card))
+    }
+
+    /// Groups of equal non-wild cards, largest and then strongest first, and the wildcard count.
+    fn groups(&self, cards: &[char]) -> (Vec<(char, usize)>, usize) {
+        let mut card_frequency = HashMap::new();
+        let mut wildcards = 0;
+        for card in cards {
+            if self.wildcards.contains(card) {
+                wildcards += 1;
+            } else {
+                *card_frequency.entry(*card).or_insert(0) += 1;
+            }
+        }
+        let mut groups = card_frequency.into_iter().collect::<Vec<(char, usize)>>();
+        groups.sort_by_key(|(card, size)| Reverse((*size, self.strength(*card))));
+        (groups, wildcards)
+    }
+
+    /// Non-wild labels that don't appear among `cards`, strongest first.
+    fn spare_labels<'a>(&'a self, cards: &'a [char]) -> impl Iterator<Item = &'a char> {
+        self.order
+            .iter()
+            .rev()
+            .filter(|card| !self.wildcards.contains(card) && !cards.contains(card))
+    }
+
+    /// Index into `ranking` of the strongest type the cards fit.
+    fn classify(&self, cards: &[char]) -> usize {
+        let (groups, wildcards) = self.groups(cards);
+        let sizes = groups.iter().map(|(_, size)| *size).collect::<Vec<_>>();
+        let spare_labels = self.spare_labels(cards).count();
+        self.ranking
+            .iter()
+            .rposition(|kind| kind.fits(&sizes, wildcards, spare_labels))
+            .unwrap_or_else(|| panic!("No hand type fits: {:?}", cards))
+    }
+
+    /// The cards the wildcards in `hand` are played as, ordered by the pattern group they
+    /// complete (largest first), then any left over as the strongest group's card.
+    fn substitutes(&self, hand: &Hand) -> Vec<char> {
+        let (groups, wildcards) = self.groups(&hand.cards);
+        let mut unused = self.spare_labels(&hand.cards);
+        let mut substitutes = Vec::new();
+        for (i, size) in self.ranking[hand.kind].pattern.iter().enumerate() {
+            let (card, present) = groups
+                .get(i)
+                .copied()
+                .unwrap_or_else(|| (*unused.next().expect("Type fits with spare labels"), 0));
+            substitutes.extend(std::iter::repeat_n(card, size.saturating_sub(present)));
+        }
+        let strongest = groups
+            .first()
+            .map(|(card, _)| *card)
+            .or_else(|| substitutes.first().copied())
+            .unwrap_or(*self.order.last().unwrap());
+        substitutes.resize(wildcards, strongest);
+        substitutes
+    }
+
+    pub fn hand(&self, s: &str) -> Hand {
+        let split: Vec<&str> = s.split(' ').collect();
+        let cards: Vec<char> = split[0].chars().collect();
+        if cards.len() != self.hand_size {
+            panic!("Expected {} cards: {}", self.hand_size, s);
+        }
+        Hand {
+            kind: self.classify(&cards),
+            strengths: cards.iter().map(|card| self.strength(*card)).collect(),
+            cards,
+            bid: split[1].parse().unwrap(),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
-struct Hand {
-    hand: Type,
+pub struct Hand {
+    pub cards: Vec<char>,
+    /// Index of the hand's type in the ruleset's ranking.
+    pub kind: usize,
+    strengths: Vec<usize>,
     pub bid: u64,
 }
 
 impl Ord for Hand {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.hand.cmp(&other.hand)
+        self.kind
+            .cmp(&other.kind)
+            .then_with(|| self.strengths.cmp(&other.strengths))
     }
 }
 
@@ -113,19 +191,6 @@ impl PartialOrd for Hand {
     }
 }
 
-impl FromStr for Hand {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let split: Vec<&str> = s.split(' ').collect();
-        let cards: Vec<Card> = split[0].chars().map(Card::from).collect();
-        Ok(Hand {
-            hand: Type::from_cards(&cards),
-            bid: split[1].parse().unwrap(),
-        })
-    }
-}
-
 fn parse_input(input: Vec<String>) -> Vec<String> {
     input
 }
@@ -141,39 +206,170 @@ pub fn run_day() {
     );
 }
 
-fn play_cards(input: &[Hand]) -> u64 {
-    let mut input = input.to_vec();
-    input.sort_by(|a, b| b.cmp(a));
-    input
+/// A hand's place in the final ordering and why it ended up there.
+#[derive(Clone, Debug)]
+pub struct RankedHand {
+    pub rank: usize,
+    pub hand: Hand,
+    pub kind: Type,
+    /// What the wildcards in the hand were played as, see `Ruleset::substitutes`.
+    pub substitutes: Vec<char>,
+    /// Card position that separates this hand from the next weaker one, if both share a type.
+    pub tie_break: Option<usize>,
+}
+
+impl fmt::Display for RankedHand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>5} {} {:>5} {}",
+            self.rank,
+            self.hand.cards.iter().collect::<String>(),
+            self.hand.bid,
+            self.kind.name
+        )?;
+        if !self.substitutes.is_empty() {
+            write!(
+                f,
+                ", wildcards as {}",
+                self.substitutes.iter().collect::<String>()
+            )?;
+        }
+        if let Some(position) = self.tie_break {
+            write!(f, ", beats previous at card {}", position + 1)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn rank_hands(input: &[String], rules: &Ruleset) -> Vec<RankedHand> {
+    let mut hands = input.iter().map(|s| rules.hand(s)).collect::<Vec<_>>();
+    hands.sort();
+    hands
         .iter()
-        .rev()
         .enumerate()
-        .map(|(rank, hand)| (rank + 1) as u64 * hand.bid)
+        .map(|(i, hand)| RankedHand {
+            rank: i + 1,
+            hand: hand.clone(),
+            kind: rules.ranking[hand.kind].clone(),
+            substitutes: rules.substitutes(hand),
+            tie_break: i
+                .checked_sub(1)
+                .map(|previous| &hands[previous])
+                .filter(|previous| previous.kind == hand.kind)
+                .and_then(|previous| {
+                    zip(&previous.strengths, &hand.strengths).position(|(a, b)| a != b)
+                }),
+        })
+        .collect()
+}
+
+fn play_cards(input: &[String], rules: &Ruleset) -> u64 {
+    rank_hands(input, rules)
+        .iter()
+        .map(|ranked| ranked.rank as u64 * ranked.hand.bid)
         .sum()
 }
 
 fn part1(input: &[String]) -> u64 {
-    play_cards(
-        &input
-            .iter()
-            .map(|s| Hand::from_str(s).unwrap())
-            .collect::<Vec<_>>(),
-    )
+    play_cards(input, &Ruleset::classic())
 }
 
 fn part2(input: &[String]) -> u64 {
-    play_cards(
-        &input
-            .iter()
-            .map(|s| s.replace('J', "X"))
-            .map(|s| Hand::from_str(&s).unwrap())
-            .collect::<Vec<_>>(),
-    )
+    play_cards(input, &Ruleset::jokers())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    #[test]
+    fn part1_sample() {
+        let input = sample_lines(
+            r"32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483
+",
+        );
+        assert_eq!(6440, part1(&input));
+        assert_eq!(5905, part2(&input));
+    }
+
+    #[test]
+    fn ranked_table() {
+        let input = sample_lines(
+            r"32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483
+",
+        );
+        let table = rank_hands(&input, &Ruleset::jokers());
+        let cards = table
+            .iter()
+            .map(|ranked| ranked.hand.cards.iter().collect::<String>())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["32T3K", "KK677", "T55J5", "QQQJA", "KTJJT"], cards);
+        assert_eq!("Four of a kind", table[4].kind.name);
+        assert_eq!(vec!['T', 'T'], table[4].substitutes);
+        assert_eq!(vec!['5'], table[2].substitutes);
+        assert_eq!(None, table[2].tie_break);
+        assert_eq!(Some(0), table[3].tie_break);
+        assert_eq!(
+            "    5 KTJJT   220 Four of a kind, wildcards as TT, beats previous at card 1",
+            table[4].to_string()
+        );
+    }
+
+    #[test]
+    fn house_rules() {
+        let rules = Ruleset {
+            order: "*123456".chars().collect(),
+            wildcards: vec!['*'],
+            hand_size: 4,
+            ranking: vec![
+                Type::new("Nothing", &[1]),
+                Type::new("Pair", &[2]),
+                Type::new("Triple", &[3]),
+                Type::new("Two pair", &[2, 2]),
+                Type::new("Quad", &[4]),
+            ],
+        };
+        let kind = |hand: &str| rules.ranking[rules.hand(hand).kind].name.clone();
+        assert_eq!("Nothing", kind("1234 0"));
+        assert_eq!("Two pair", kind("1122 0"));
+        assert_eq!("Two pair", kind("11*2 0"));
+        assert_eq!("Quad", kind("1**1 0"));
+        let table = rank_hands(&sample_lines("11*2 0\n1*** 0\n*5*6 0"), &rules);
+        assert_eq!(vec!['6', '5'], table[0].substitutes);
+        assert_eq!(vec!['2'], table[1].substitutes);
+        assert_eq!(vec!['1', '1', '1'], table[2].substitutes);
+        assert_eq!(
+            1 + 2 * 2 + 3 * 3,
+            play_cards(&sample_lines("1112 2\n1122 3\n6543 1"), &rules)
+        );
+
+        // Three singles need three labels, but only two exist
+        let rules = Ruleset {
+            order: "*12".chars().collect(),
+            wildcards: vec!['*'],
+            hand_size: 3,
+            ranking: vec![
+                Type::new("Single", &[1]),
+                Type::new("Triple", &[3]),
+                Type::new("Rainbow", &[1, 1, 1]),
+            ],
+        };
+        let table = rank_hands(&sample_lines("*** 0\n12* 0"), &rules);
+        assert_eq!("Single", table[0].kind.name);
+        assert_eq!(vec!['2'], table[0].substitutes);
+        assert_eq!("Triple", table[1].kind.name);
+        assert_eq!(vec!['2', '2', '2'], table[1].substitutes);
+    }
 
     #[test]
     fn part1_output() {
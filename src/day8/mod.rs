@@ -1,4 +1,4 @@
-use num::Integer;
+use num::integer::{ExtendedGcd, Integer};
 use std::collections::HashMap;
 
 use aoc_downloader::download_day;
@@ -17,7 +17,96 @@ fn get_input() -> Vec<String> {
 type Map = HashMap<String, (String, String)>;
 type Directions = Vec<char>;
 
-fn parse_input(input: Vec<String>) -> (Directions, Map) {
+/// The network with dense node ids and decoded instructions.
+#[derive(Debug)]
+pub struct Network {
+    pub names: Vec<String>,
+    /// Left and right neighbour of every node.
+    pub edges: Vec<[u32; 2]>,
+    /// Instructions as indices into a node's edges.
+    pub instructions: Vec<u8>,
+    /// Node reached from every node after following all instructions once.
+    pub pass: Vec<u32>,
+}
+
+impl Network {
+    fn compile(directions: &Directions, map: &Map) -> Self {
+        let mut names = map.keys().cloned().collect::<Vec<String>>();
+        names.sort();
+        let ids = names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| (name.as_str(), id as u32))
+            .collect::<HashMap<&str, u32>>();
+        let edges = names
+            .iter()
+            .map(|name| {
+                let (left, right) = &map[name];
+                [ids[left.as_str()], ids[right.as_str()]]
+            })
+            .collect::<Vec<_>>();
+        let instructions = directions
+            .iter()
+            .map(|direction| match direction {
+                'L' => 0,
+                'R' => 1,
+                c => panic!("Unexpected character: {}", c),
+            })
+            .collect::<Vec<u8>>();
+        let pass = (0..names.len() as u32)
+            .map(|node| {
+                instructions
+                    .iter()
+                    .fold(node, |node, turn| edges[node as usize][*turn as usize])
+            })
+            .collect();
+        Network {
+            names,
+            edges,
+            instructions,
+            pass,
+        }
+    }
+
+    pub fn id(&self, name: &str) -> Option<u32> {
+        self.names
+            .binary_search_by(|probe| probe.as_str().cmp(name))
+            .ok()
+            .map(|id| id as u32)
+    }
+
+    /// Marks every node whose name satisfies `goal`.
+    pub fn goals(&self, goal: fn(&str) -> bool) -> Vec<bool> {
+        self.names.iter().map(|name| goal(name)).collect()
+    }
+
+    fn step(&self, node: u32, steps: usize) -> u32 {
+        self.edges[node as usize][self.instructions[steps % self.instructions.len()] as usize]
+    }
+
+    /// Node reached from `start` after `steps` steps.
+    pub fn walk(&self, start: u32, steps: usize) -> u32 {
+        let mut passes = steps / self.instructions.len();
+        let mut seen = vec![None; self.names.len()];
+        let mut node = start;
+        let mut pass = 0;
+        while pass < passes {
+            // Whole passes loop through the nodes as well, skip every full round of that loop
+            if let Some(first) = seen[node as usize].replace(pass) {
+                passes = pass + (passes - pass) % (pass - first);
+                seen.fill(None);
+                if pass == passes {
+                    break;
+                }
+            }
+            node = self.pass[node as usize];
+            pass += 1;
+        }
+        (0..steps % self.instructions.len()).fold(node, |node, step| self.step(node, step))
+    }
+}
+
+pub fn parse_input(input: Vec<String>) -> Network {
     let re = regex!(r"(\w{3}) = \((\w{3}), (\w{3})\)");
     let directions = input[0].chars().collect();
     let mut map = HashMap::new();
@@ -34,7 +123,7 @@ fn parse_input(input: Vec<String>) -> (Directions, Map) {
             );
         });
 
-    (directions, map)
+    Network::compile(&directions, &map)
 }
 
 pub fn run_day() {
@@ -48,37 +137,244 @@ pub fn run_day() {
     );
 }
 
-fn count_steps(start: &str, goal: fn(&str) -> bool, map: &Map, directions: &Directions) -> usize {
-    let mut pos = start.to_string();
+fn count_steps(start: u32, goals: &[bool], network: &Network) -> usize {
+    let mut pos = start;
     let mut steps = 0;
-    while goal(&pos) {
-        let direction = directions[steps % directions.len()];
-        pos = match direction {
-            'L' => map.get(&pos).unwrap().0.clone(),
-            'R' => map.get(&pos).unwrap().1.clone(),
-            c => panic!("Unexpected character: {}", c),
-        };
+    while !goals[pos as usize] {
+        pos = network.step(pos, steps);
         steps += 1;
     }
     steps
 }
 
-fn part1(input: &(Directions, Map)) -> usize {
-    let (directions, map) = input;
-    count_steps("AAA", |pos| pos != "ZZZ", map, directions)
+/// How a walk through the network settles into a loop.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cycle {
+    /// Steps taken before the walk enters its loop.
+    pub pre_period: usize,
+    pub period: usize,
+    /// Steps before the first repeated state that end on a goal node.
+    pub hits: Vec<usize>,
 }
 
-fn part2(input: &(Directions, Map)) -> usize {
-    let (directions, map) = input;
-    map.keys()
-        .filter(|node| node.ends_with('A'))
-        .map(|pos| count_steps(pos, |pos| !pos.ends_with('Z'), map, directions))
-        .fold(1, |lcm, clen| lcm.lcm(&clen))
+impl Cycle {
+    fn is_hit(&self, steps: usize) -> bool {
+        if steps < self.pre_period + self.period {
+            return self.hits.contains(&steps);
+        }
+        self.hits
+            .iter()
+            .filter(|hit| **hit >= self.pre_period)
+            .any(|hit| Integer::is_multiple_of(&(steps - hit), &self.period))
+    }
+}
+
+/// Walks from `start` until a (node, instruction index) state repeats.
+pub fn find_cycle(start: u32, goals: &[bool], network: &Network) -> Cycle {
+    let length = network.instructions.len();
+    let mut seen = vec![None; network.names.len() * length];
+    let mut hits = Vec::new();
+    let mut pos = start;
+    let mut steps = 0;
+    loop {
+        let state = pos as usize * length + steps % length;
+        if let Some(first) = seen[state] {
+            return Cycle {
+                pre_period: first,
+                period: steps - first,
+                hits,
+            };
+        }
+        seen[state] = Some(steps);
+        if goals[pos as usize] {
+            hits.push(steps);
+        }
+        pos = network.step(pos, steps);
+        steps += 1;
+    }
+}
+
+/// Merges `x = a.0 mod a.1` and `x = b.0 mod b.1`, the moduli need not be coprime.
+fn crt(a: (i128, i128), b: (i128, i128)) -> Option<(i128, i128)> {
+    let ExtendedGcd { gcd, x, .. } = a.1.extended_gcd(&b.1);
+    if (b.0 - a.0) % gcd != 0 {
+        return None;
+    }
+    let modulus = a.1 / gcd * b.1;
+    let t = ((b.0 - a.0) / gcd * x).mod_floor(&(b.1 / gcd));
+    Some(((a.0 + a.1 * t).mod_floor(&modulus), modulus))
+}
+
+/// First step count at which every walk stands on a goal node.
+pub fn first_common_hit(cycles: &[Cycle]) -> Option<usize> {
+    let settled = cycles
+        .iter()
+        .map(|cycle| cycle.pre_period)
+        .max()
+        .unwrap_or(0);
+    // Without any walks there is nothing to wait for
+    let Some(first) = cycles.first() else {
+        return Some(0);
+    };
+    // Hits before all walks are looping can't be expressed as congruences, check them directly
+    let early = first
+        .hits
+        .iter()
+        .flat_map(|hit| {
+            let repeat = if *hit < first.pre_period {
+                settled
+            } else {
+                first.period
+            };
+            (*hit..settled).step_by(repeat)
+        })
+        .filter(|steps| cycles.iter().all(|cycle| cycle.is_hit(*steps)))
+        .min();
+    if early.is_some() {
+        return early;
+    }
+    cycles
+        .iter()
+        .map(|cycle| {
+            cycle
+                .hits
+                .iter()
+                .filter(|hit| **hit >= cycle.pre_period)
+                .map(|hit| (*hit as i128, cycle.period as i128))
+                .collect::<Vec<_>>()
+        })
+        .fold(vec![(0, 1)], |solutions, congruences| {
+            solutions
+                .iter()
+                .flat_map(|a| congruences.iter().filter_map(|b| crt(*a, *b)))
+                .collect()
+        })
+        .into_iter()
+        .map(|(residue, modulus)| {
+            let settled = settled as i128;
+            if residue >= settled {
+                residue
+            } else {
+                residue + (settled - residue).div_ceil(modulus) * modulus
+            }
+        })
+        .min()
+        .map(|steps| steps as usize)
+}
+
+fn part1(input: &Network) -> usize {
+    let goals = input.goals(|pos| pos == "ZZZ");
+    count_steps(input.id("AAA").unwrap(), &goals, input)
+}
+
+fn part2(input: &Network) -> usize {
+    let goals = input.goals(|pos| pos.ends_with('Z'));
+    let cycles = input
+        .names
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| node.ends_with('A'))
+        .map(|(start, _)| find_cycle(start as u32, &goals, input))
+        .collect::<Vec<_>>();
+    first_common_hit(&cycles).expect("Ghosts never meet on Z nodes")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    #[test]
+    fn part2_sample() {
+        let input = parse_input(sample_lines(
+            r"LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)",
+        ));
+        assert_eq!(6, part2(&input));
+    }
+
+    #[test]
+    fn compiled_network() {
+        let input = parse_input(sample_lines(
+            r"LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)",
+        ));
+        let id = |name| input.id(name).unwrap();
+        assert_eq!(vec![[1, 1], [0, 2], [2, 2]], input.edges);
+        assert_eq!(vec![0, 0, 1], input.instructions);
+        assert_eq!(vec![id("BBB"), id("ZZZ"), id("ZZZ")], input.pass);
+        for steps in 0..20 {
+            let stepped = (0..steps).fold(id("AAA"), |node, step| input.step(node, step));
+            assert_eq!(stepped, input.walk(id("AAA"), steps));
+        }
+        assert_eq!(id("ZZZ"), input.walk(id("AAA"), 6));
+        assert_eq!(id("ZZZ"), input.walk(id("AAA"), 3_000_000_001));
+        assert_eq!(6, part1(&input));
+    }
+
+    #[test]
+    fn offset_cycles() {
+        let input = parse_input(sample_lines(
+            r"L
+
+11A = (11Z, 11Z)
+11Z = (11B, 11B)
+11B = (11C, 11C)
+11C = (11Z, 11Z)
+22A = (22B, 22B)
+22B = (22Z, 22Z)
+22Z = (22B, 22B)",
+        ));
+        let goals = input.goals(|pos| pos.ends_with('Z'));
+        assert_eq!(
+            Cycle {
+                pre_period: 1,
+                period: 3,
+                hits: vec![1]
+            },
+            find_cycle(input.id("11A").unwrap(), &goals, &input)
+        );
+        assert_eq!(4, part2(&input));
+        // The first walk's only early hit must not be mistaken for a repeating one
+        let cycles = [
+            Cycle {
+                pre_period: 2,
+                period: 5,
+                hits: vec![0],
+            },
+            Cycle {
+                pre_period: 0,
+                period: 1,
+                hits: vec![0],
+            },
+        ];
+        assert_eq!(Some(0), first_common_hit(&cycles));
+        let cycles = [
+            Cycle {
+                pre_period: 6,
+                period: 2,
+                hits: vec![0],
+            },
+            Cycle {
+                pre_period: 0,
+                period: 10,
+                hits: vec![4],
+            },
+        ];
+        assert_eq!(None, first_common_hit(&cycles));
+        assert_eq!(Some(0), first_common_hit(&[]));
+    }
 
     #[test]
     fn part1_output() {
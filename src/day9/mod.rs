@@ -1,6 +1,7 @@
 use aoc_downloader::download_day;
 use itertools::Itertools;
-use std::ops::{Add, Sub};
+use num::{BigInt, One, ToPrimitive, Zero};
+use std::fmt;
 
 const DAY: u32 = 9;
 
@@ -13,7 +14,7 @@ fn get_input() -> Vec<String> {
     reader.lines().collect::<Result<_, _>>().unwrap()
 }
 
-fn parse_input(input: Vec<String>) -> Vec<Vec<i64>> {
+pub fn parse_input(input: Vec<String>) -> Vec<Vec<i64>> {
     input
         .iter()
         .map(|line| {
@@ -35,46 +36,150 @@ pub fn run_day() {
     );
 }
 
-trait Derive {
-    fn derive(&self) -> Vec<i64>;
+/// The differences of a sequence did not reach zero before running out of values.
+#[derive(Debug, PartialEq, Eq)]
+pub struct NotPolynomial {
+    pub length: usize,
 }
 
-impl Derive for &Vec<i64> {
-    fn derive(&self) -> Vec<i64> {
-        self.iter().map_windows(|[&a, &b]| b - a).collect_vec()
+impl fmt::Display for NotPolynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Sequence of {} values is not a polynomial of degree below {}",
+            self.length,
+            self.length.saturating_sub(1)
+        )
     }
 }
 
-fn polate(sequence: &[i64], op: fn(i64, &Vec<i64>) -> i64) -> i64 {
-    let mut stack: Vec<Vec<i64>> = vec![sequence.to_vec()];
-    while stack.last().unwrap().iter().any(|n| *n != 0) {
-        stack.push(stack.last().unwrap().derive());
-    }
-    stack.iter().rev().fold(0, op)
+/// The polynomial through a sequence in Newton's forward-difference form.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Polynomial {
+    /// First entry of every difference row, `p(x) = sum(c_j * binomial(x, j))`.
+    pub coefficients: Vec<BigInt>,
+    /// Number of values the polynomial was fitted to.
+    pub length: usize,
 }
 
-fn forward(acc: i64, sequence: &Vec<i64>) -> i64 {
-    sequence.last().unwrap().add(acc)
-}
+impl Polynomial {
+    /// Fits `sequence`, which needs a row of zeros among its differences to pin down the degree.
+    pub fn fit(sequence: &[i64]) -> Result<Self, NotPolynomial> {
+        let mut row = sequence.iter().map(|n| BigInt::from(*n)).collect_vec();
+        let mut coefficients = Vec::new();
+        while row.iter().any(|n| !n.is_zero()) {
+            if row.len() == 1 {
+                return Err(NotPolynomial {
+                    length: sequence.len(),
+                });
+            }
+            coefficients.push(row[0].clone());
+            row = row.iter().map_windows(|[a, b]| *b - *a).collect_vec();
+        }
+        Ok(Polynomial {
+            coefficients,
+            length: sequence.len(),
+        })
+    }
 
-fn part1(input: &[Vec<i64>]) -> i64 {
-    input.iter().map(|sequence| polate(sequence, forward)).sum()
+    /// Degree of the polynomial, `None` for a sequence of zeros.
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    /// Value at index `x` of the sequence, which may lie outside of the fitted values.
+    pub fn eval(&self, x: i64) -> BigInt {
+        let x = BigInt::from(x);
+        let mut binomial = BigInt::one();
+        let mut value = BigInt::zero();
+        for (j, coefficient) in self.coefficients.iter().enumerate() {
+            if j > 0 {
+                binomial = binomial * (&x - (j - 1)) / j;
+            }
+            value += coefficient * &binomial;
+        }
+        value
+    }
+
+    /// Value `steps` places after the last fitted one.
+    pub fn ahead(&self, steps: i64) -> BigInt {
+        self.eval(self.length as i64 - 1 + steps)
+    }
+
+    /// Value `steps` places before the first fitted one.
+    pub fn behind(&self, steps: i64) -> BigInt {
+        self.eval(-steps)
+    }
 }
 
-fn backward(acc: i64, sequence: &Vec<i64>) -> i64 {
-    sequence.first().unwrap().sub(acc)
+fn part1(input: &[Vec<i64>]) -> i64 {
+    input
+        .iter()
+        .map(|sequence| {
+            Polynomial::fit(sequence)
+                .unwrap_or_else(|e| panic!("{}", e))
+                .ahead(1)
+        })
+        .sum::<BigInt>()
+        .to_i64()
+        .unwrap()
 }
 
 fn part2(input: &[Vec<i64>]) -> i64 {
     input
         .iter()
-        .map(|sequence| polate(sequence, backward))
-        .sum()
+        .map(|sequence| {
+            Polynomial::fit(sequence)
+                .unwrap_or_else(|e| panic!("{}", e))
+                .behind(1)
+        })
+        .sum::<BigInt>()
+        .to_i64()
+        .unwrap()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::sample_lines;
+
+    #[test]
+    fn sample() {
+        let input = parse_input(sample_lines(
+            r"0 3 6 9 12 15
+1 3 6 10 15 21
+10 13 16 21 30 45",
+        ));
+        assert_eq!(114, part1(&input));
+        assert_eq!(2, part2(&input));
+    }
+
+    #[test]
+    fn polynomial_fit() {
+        let squares = Polynomial::fit(&[1, 4, 9, 16]).unwrap();
+        assert_eq!(Some(2), squares.degree());
+        assert_eq!(BigInt::from(49), squares.ahead(3));
+        assert_eq!(BigInt::from(0), squares.behind(1));
+        assert_eq!(BigInt::from(16), squares.behind(5));
+        let cubes = Polynomial::fit(&[0, 1, 8, 27, 64]).unwrap();
+        assert_eq!(Some(3), cubes.degree());
+        assert_eq!(BigInt::from(10).pow(30), cubes.eval(10_i64.pow(10)));
+        assert_eq!(None, Polynomial::fit(&[0, 0, 0]).unwrap().degree());
+    }
+
+    #[test]
+    fn reject_noisy_sequences() {
+        assert_eq!(
+            Err(NotPolynomial { length: 4 }),
+            Polynomial::fit(&[1, 4, 9, 17])
+        );
+        assert_eq!(
+            Err(NotPolynomial { length: 5 }),
+            Polynomial::fit(&[1, 2, 4, 8, 16])
+        );
+        assert_eq!(Some(1), Polynomial::fit(&[3, 5, 7]).unwrap().degree());
+        assert!(Polynomial::fit(&[3, 5]).is_err());
+    }
 
     #[test]
     fn part1_output() {
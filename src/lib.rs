@@ -1,14 +1,14 @@
 #![feature(int_roundings)]
-#![feature(isqrt)]
 #![feature(iter_map_windows)]
 #[macro_use]
 mod utils;
+pub mod cycle;
 mod day1;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
 mod day15;
 mod day16;
 mod day17;
@@ -21,13 +21,13 @@ mod day22;
 mod day23;
 mod day24;
 mod day25;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod day7;
+pub mod day8;
+pub mod day9;
 
 pub fn get_days() -> Vec<fn()> {
     vec![
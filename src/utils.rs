@@ -4,3 +4,9 @@ macro_rules! regex {
         RE.get_or_init(|| regex::Regex::new($re).unwrap())
     }};
 }
+
+/// Input lines of a puzzle sample written out in a test.
+#[cfg(test)]
+pub fn sample_lines(sample: &str) -> Vec<String> {
+    sample.lines().map(|line| line.to_string()).collect()
+}